@@ -3,15 +3,15 @@ use clap::Parser;
 use core::str;
 use log::{debug, error};
 use std::{
-    io::{ErrorKind, Seek},
+    io::ErrorKind,
     net::Ipv4Addr,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::OnceLock,
 };
 use tokio_stream::StreamExt;
 use trale::{
     futures::{
-        fs::File,
+        fs,
         read::AsyncRead,
         tcp::{TcpListener, TcpStream},
         write::AsyncWrite,
@@ -80,23 +80,14 @@ async fn send_response_hdr(
 }
 
 async fn send_file(mut conn: TcpStream, path: PathBuf) -> anyhow::Result<()> {
-    match File::open(path).await {
-        Ok(mut f) => {
-            let len = f.seek(std::io::SeekFrom::End(0))? as usize;
-
-            f.seek(std::io::SeekFrom::Start(0))?;
-
-            send_response_hdr(&mut conn, Response::Ok, len).await?;
-
-            loop {
-                let mut buf = [0; 4096];
-                let len = f.read(&mut buf).await?;
-
-                if len == 0 {
-                    break;
-                }
-
-                conn.write(&buf).await?;
+    match fs::read(path).await {
+        Ok(contents) => {
+            send_response_hdr(&mut conn, Response::Ok, contents.len()).await?;
+
+            let mut buf = contents.as_slice();
+            while !buf.is_empty() {
+                let len = conn.write(buf).await?;
+                buf = &buf[len..];
             }
 
             Ok(())
@@ -108,6 +99,48 @@ async fn send_file(mut conn: TcpStream, path: PathBuf) -> anyhow::Result<()> {
     }
 }
 
+/// Escape `s` so that it can be placed in HTML text or a quoted attribute.
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+async fn send_dir_index(mut conn: TcpStream, dir: PathBuf) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(&dir);
+    let mut body = String::from("<html><body><ul>\n");
+
+    while let Some(entry) = entries.next().await {
+        let entry = entry?;
+        let mut name = entry.file_name().to_string_lossy().into_owned();
+
+        if entry.file_type().await?.is_dir() {
+            name.push('/');
+        }
+
+        let name = escape_html(&name);
+        body.push_str(&format!("<li><a href=\"{name}\">{name}</a></li>\n"));
+    }
+
+    body.push_str("</ul></body></html>\n");
+
+    send_response_hdr(&mut conn, Response::Ok, body.len()).await?;
+    conn.write(body.as_bytes()).await?;
+
+    Ok(())
+}
+
 async fn handle_connection(mut conn: TcpStream) -> anyhow::Result<()> {
     debug!("New Connection");
     let mut buf = [0; 1024];
@@ -132,7 +165,7 @@ async fn handle_connection(mut conn: TcpStream) -> anyhow::Result<()> {
 
     let path = PathBuf::from(path);
 
-    let file = if path == PathBuf::from("/") {
+    let file = if path == Path::new("/") {
         ARGS.get().unwrap().webroot.join("index.html")
     } else if let Ok(path) = path.strip_prefix("/") {
         ARGS.get().unwrap().webroot.join(path)
@@ -140,6 +173,10 @@ async fn handle_connection(mut conn: TcpStream) -> anyhow::Result<()> {
         return send_response_hdr(&mut conn, Response::NotFound, 0).await;
     };
 
+    if fs::metadata(&file).await.is_ok_and(|m| m.is_dir()) {
+        return send_dir_index(conn, file).await;
+    }
+
     send_file(conn, file).await
 }
 
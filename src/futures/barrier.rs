@@ -0,0 +1,230 @@
+//! ### Async Barriers
+//!
+//! A [Barrier] lets a fixed number of tasks wait until all of them have
+//! reached the same point, for example the end of one phase of a batch job,
+//! before any of them continue. Tasks that arrive early yield rather than
+//! blocking the executor.
+//!
+//! Exactly one task from each round is chosen as the *leader*, as reported by
+//! [BarrierWaitResult::is_leader], which is handy for performing some work
+//! once per phase. A barrier can be reused for any number of rounds.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::barrier::Barrier;
+//! use std::sync::Arc;
+//!
+//! let barrier = Arc::new(Barrier::new(4));
+//!
+//! let tasks: Vec<_> = (0..4)
+//!     .map(|_| {
+//!         let barrier = barrier.clone();
+//!         Executor::spawn(async move { barrier.wait().await.is_leader() })
+//!     })
+//!     .collect();
+//!
+//! Executor::run();
+//!
+//! let leaders = tasks.into_iter().map(|t| t.join()).filter(|l| *l);
+//! assert_eq!(leaders.count(), 1);
+//! ```
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use super::waiters::WaitList;
+
+struct State {
+    arrived: usize,
+    waiters: WaitList<()>,
+}
+
+/// A barrier which releases tasks once a given number of them are waiting.
+///
+/// See the [module-level documentation](self) for more information.
+pub struct Barrier {
+    n: usize,
+    state: Mutex<State>,
+}
+
+/// The result of [Barrier::wait].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` for exactly one task in each round.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Create a barrier which releases tasks in groups of `n`.
+    ///
+    /// A barrier of size zero behaves like one of size one.
+    pub const fn new(n: usize) -> Self {
+        Self {
+            n: if n == 0 { 1 } else { n },
+            state: Mutex::new(State {
+                arrived: 0,
+                waiters: WaitList::new(),
+            }),
+        }
+    }
+
+    /// Wait until all `n` tasks have called this function.
+    ///
+    /// The last task to arrive is the leader of the round and does not
+    /// yield. If the returned future is dropped before the round completes,
+    /// the task no longer counts towards it.
+    pub async fn wait(&self) -> BarrierWaitResult {
+        BarrierWait {
+            barrier: self,
+            key: None,
+        }
+        .await
+    }
+}
+
+struct BarrierWait<'a> {
+    barrier: &'a Barrier,
+    key: Option<usize>,
+}
+
+impl Future for BarrierWait<'_> {
+    type Output = BarrierWaitResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.barrier.state.lock().unwrap();
+
+        match self.key {
+            None => {
+                state.arrived += 1;
+
+                if state.arrived == self.barrier.n {
+                    state.arrived = 0;
+                    while state.waiters.notify_front() {}
+                    return Poll::Ready(BarrierWaitResult(true));
+                }
+
+                let key = state.waiters.push((), cx.waker());
+                drop(state);
+                self.key = Some(key);
+
+                Poll::Pending
+            }
+            Some(key) if state.waiters.is_notified(key) => {
+                state.waiters.remove(key);
+                drop(state);
+                self.key = None;
+
+                Poll::Ready(BarrierWaitResult(false))
+            }
+            Some(key) => {
+                state.waiters.register(key, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for BarrierWait<'_> {
+    fn drop(&mut self) {
+        let Some(key) = self.key else {
+            return;
+        };
+
+        let mut state = self.barrier.state.lock().unwrap();
+
+        if let (_, false) = state.waiters.remove(key) {
+            state.arrived -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Barrier;
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::{Arc, Mutex},
+        task::{Context, Waker},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn phases() {
+        let barrier = Arc::new(Barrier::new(3));
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let tasks: Vec<_> = (0..3)
+            .map(|i| {
+                let barrier = barrier.clone();
+                let log = log.clone();
+                Executor::spawn(async move {
+                    let mut leaders = 0;
+                    for phase in 0..2 {
+                        Timer::sleep(Duration::from_millis(i * 5)).unwrap().await;
+                        log.lock().unwrap().push(phase);
+                        if barrier.wait().await.is_leader() {
+                            leaders += 1;
+                        }
+                    }
+                    leaders
+                })
+            })
+            .collect();
+
+        Executor::run();
+
+        let leaders: usize = tasks.into_iter().map(|t| t.join()).sum();
+        assert_eq!(leaders, 2);
+        assert_eq!(*log.lock().unwrap(), [0, 0, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn cancelled_wait() {
+        let barrier = Barrier::new(2);
+
+        {
+            let mut wait = pin!(barrier.wait());
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(wait.as_mut().poll(&mut cx).is_pending());
+        }
+
+        // The cancelled wait doesn't count, so the round is still open.
+        let mut wait = pin!(barrier.wait());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(wait.as_mut().poll(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn cross_thread() {
+        let barrier = Arc::new(Barrier::new(4));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    Executor::block_on(async move { barrier.wait().await.is_leader() })
+                })
+            })
+            .collect();
+
+        let leaders = threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .filter(|l| *l)
+            .count();
+
+        assert_eq!(leaders, 1);
+    }
+}
@@ -0,0 +1,400 @@
+//! ### Broadcast Channels
+//!
+//! A broadcast channel delivers every value sent by any [Sender] to every
+//! [Receiver]. Values are kept in a ring buffer of fixed capacity; once it is
+//! full, sending a new value overwrites the oldest one. A receiver which falls
+//! so far behind that values it has not yet seen are overwritten is said to
+//! *lag*: its next call to [Receiver::recv] returns [RecvError::Lagged] with
+//! the number of values it missed, and subsequent calls continue from the
+//! oldest value still held.
+//!
+//! Values are cloned for each receiver, so `T` must implement [Clone].
+//! Further receivers are created with [Sender::subscribe] and only see values
+//! sent after they subscribed.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::channel::broadcast;
+//!
+//! Executor::block_on(async {
+//!     let (tx, mut rx1) = broadcast::channel(16);
+//!     let mut rx2 = tx.subscribe();
+//!
+//!     let task = Executor::spawn(async move {
+//!         assert_eq!(rx2.recv().await, Ok("reload"));
+//!     });
+//!
+//!     tx.send("reload").unwrap();
+//!
+//!     assert_eq!(rx1.recv().await, Ok("reload"));
+//!     task.await;
+//! });
+//! ```
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Display},
+    sync::{Arc, Mutex},
+};
+
+use crate::futures::notify::Notify;
+
+struct State<T> {
+    buffer: VecDeque<T>,
+    capacity: usize,
+    /// The position of the oldest value in `buffer`.
+    head: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T> State<T> {
+    /// The position that the next value sent will occupy.
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    notify: Notify,
+}
+
+/// The sending half of a broadcast channel.
+///
+/// Senders can be cloned to send from multiple tasks.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// The receiving half of a broadcast channel.
+///
+/// Cloning a receiver creates another receiver at the same position in the
+/// channel.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    next: u64,
+}
+
+/// The error returned by [Sender::send] when there are no receivers. The
+/// value that could not be sent is handed back to the caller.
+#[derive(PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// The error returned by [Receiver::recv].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// All senders have been dropped and every value has been received.
+    Closed,
+    /// The receiver fell behind and the given number of values were
+    /// overwritten before it could receive them.
+    Lagged(u64),
+}
+
+/// The error returned by [Receiver::try_recv].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// There are no new values.
+    Empty,
+    /// All senders have been dropped and every value has been received.
+    Closed,
+    /// The receiver fell behind and the given number of values were
+    /// overwritten before it could receive them.
+    Lagged(u64),
+}
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> Display for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+impl Display for RecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecvError::Closed => write!(f, "channel closed"),
+            RecvError::Lagged(n) => write!(f, "channel lagged by {n}"),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+impl Display for TryRecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "channel empty"),
+            TryRecvError::Closed => write!(f, "channel closed"),
+            TryRecvError::Lagged(n) => write!(f, "channel lagged by {n}"),
+        }
+    }
+}
+
+impl std::error::Error for TryRecvError {}
+
+/// Create a broadcast channel which holds up to `capacity` values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel capacity must be non-zero");
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            head: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        notify: Notify::new(),
+    });
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared, next: 0 },
+    )
+}
+
+impl<T> Sender<T> {
+    /// Send `value` to all receivers, returning the number of receivers.
+    ///
+    /// Returns the value in a [SendError] if there are no receivers. Sending
+    /// never waits; if the buffer is full the oldest value is overwritten.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if state.receivers == 0 {
+            return Err(SendError(value));
+        }
+
+        if state.buffer.len() == state.capacity {
+            state.buffer.pop_front();
+            state.head += 1;
+        }
+
+        state.buffer.push_back(value);
+
+        let receivers = state.receivers;
+
+        drop(state);
+        self.shared.notify.notify_waiters();
+
+        Ok(receivers)
+    }
+
+    /// Create a new receiver which will see all values sent after this call.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.receivers += 1;
+
+        Receiver {
+            shared: self.shared.clone(),
+            next: state.tail(),
+        }
+    }
+
+    /// The number of receivers currently subscribed to the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.state.lock().unwrap().receivers
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.senders -= 1;
+
+        if state.senders == 0 {
+            drop(state);
+            self.shared.notify.notify_waiters();
+        }
+    }
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Receive the next value, waiting for one to be sent if this receiver
+    /// has seen all values so far.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        let shared = self.shared.clone();
+
+        loop {
+            // Register interest before checking the buffer so that a value
+            // sent in between isn't missed.
+            let notified = shared.notify.notified();
+
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed),
+                Err(TryRecvError::Lagged(n)) => return Err(RecvError::Lagged(n)),
+                Err(TryRecvError::Empty) => notified.await,
+            }
+        }
+    }
+
+    /// Attempt to receive the next value without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let state = self.shared.state.lock().unwrap();
+
+        if self.next < state.head {
+            let missed = state.head - self.next;
+            self.next = state.head;
+            return Err(TryRecvError::Lagged(missed));
+        }
+
+        match state.buffer.get((self.next - state.head) as usize) {
+            Some(value) => {
+                self.next += 1;
+                Ok(value.clone())
+            }
+            None if state.senders == 0 => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().receivers += 1;
+
+        Self {
+            shared: self.shared.clone(),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().receivers -= 1;
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{channel, RecvError, TryRecvError};
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn every_receiver_sees_every_value() {
+        let (tx, rx) = channel(8);
+
+        Executor::block_on(async move {
+            let tasks: Vec<_> = (0..3)
+                .map(|_| {
+                    let mut rx = rx.clone();
+                    Executor::spawn(async move {
+                        let mut values = Vec::new();
+                        while let Ok(v) = rx.recv().await {
+                            values.push(v);
+                        }
+                        values
+                    })
+                })
+                .collect();
+
+            drop(rx);
+            Timer::sleep(Duration::from_millis(10)).unwrap().await;
+
+            for i in 0..5 {
+                assert_eq!(tx.send(i).unwrap(), 3);
+            }
+
+            drop(tx);
+
+            for task in tasks {
+                assert_eq!(task.await, [0, 1, 2, 3, 4]);
+            }
+        });
+    }
+
+    #[test]
+    fn lagged_receiver() {
+        let (tx, mut rx) = channel(2);
+
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Lagged(3)));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        let mut late = tx.subscribe();
+        drop(tx);
+        assert_eq!(late.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn no_receivers() {
+        let (tx, rx) = channel(1);
+
+        drop(rx);
+        assert_eq!(tx.send(1).map_err(|e| e.0), Err(1));
+        assert_eq!(tx.receiver_count(), 0);
+    }
+
+    #[test]
+    fn cross_thread() {
+        let (tx, mut rx) = channel(16);
+
+        let t1 = thread::spawn(move || {
+            Executor::block_on(async move {
+                let mut sum = 0;
+                loop {
+                    match rx.recv().await {
+                        Ok(v) => sum += v,
+                        Err(RecvError::Closed) => return sum,
+                        Err(RecvError::Lagged(_)) => panic!("unexpected lag"),
+                    }
+                }
+            })
+        });
+
+        for i in 0..10 {
+            thread::sleep(Duration::from_millis(2));
+            tx.send(i).unwrap();
+        }
+
+        drop(tx);
+        assert_eq!(t1.join().unwrap(), 45);
+    }
+}
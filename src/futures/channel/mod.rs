@@ -0,0 +1,18 @@
+//! Async channels for passing values between tasks.
+//!
+//! The following channel flavours are provided:
+//!
+//! - [oneshot]: Sends a single value from one task to another.
+//! - [mpsc]: A multi-producer, single-consumer queue of values, available in
+//!   bounded and unbounded variants.
+//! - [broadcast]: A multi-producer, multi-consumer queue in which every
+//!   receiver sees every value.
+//! - [watch]: Holds a single value which receivers can observe changes to.
+//!
+//! All channels can be used between tasks running on different threads, and
+//! therefore different executors; the waiting task is woken via its own
+//! executor.
+pub mod broadcast;
+pub mod mpsc;
+pub mod oneshot;
+pub mod watch;
@@ -0,0 +1,520 @@
+//! ### Multi-producer, Single-consumer Channels
+//!
+//! An mpsc channel is a queue of values sent by any number of senders and
+//! received by a single receiver. Two flavours are available:
+//!
+//! - [channel] creates a *bounded* channel which holds at most a fixed number
+//!   of values. When the channel is full, [Sender::send] waits until the
+//!   receiver makes room, providing back-pressure. Waiting senders are served
+//!   in FIFO order.
+//! - [unbounded_channel] creates a channel without a limit, so
+//!   [UnboundedSender::send] never waits and can be called from synchronous
+//!   code.
+//!
+//! The channel is closed once all senders have been dropped, at which point
+//! the receiver yields the remaining values followed by `None`. Dropping or
+//! closing the receiver causes all further sends to fail, handing the value
+//! back to the caller. Receivers also implement [Stream].
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::channel::mpsc;
+//!
+//! Executor::block_on(async {
+//!     let (tx, mut rx) = mpsc::channel(2);
+//!
+//!     for i in 0..3 {
+//!         let tx = tx.clone();
+//!         Executor::spawn(async move {
+//!             tx.send(i).await.unwrap();
+//!         });
+//!     }
+//!
+//!     drop(tx);
+//!
+//!     let mut sum = 0;
+//!     while let Some(i) = rx.recv().await {
+//!         sum += i;
+//!     }
+//!
+//!     assert_eq!(sum, 3);
+//! });
+//! ```
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Display},
+    future::poll_fn,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use tokio_stream::Stream;
+
+use crate::futures::semaphore::{Semaphore, TryAcquireError};
+
+struct State<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    rx_closed: bool,
+    rx_waker: Option<Waker>,
+}
+
+/// The state shared by all halves of a channel.
+struct Chan<T> {
+    state: Mutex<State<T>>,
+    /// One permit per free slot in a bounded channel.
+    slots: Option<Semaphore>,
+}
+
+impl<T> Chan<T> {
+    fn new(slots: Option<Semaphore>) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                senders: 1,
+                rx_closed: false,
+                rx_waker: None,
+            }),
+            slots,
+        })
+    }
+
+    /// Push a value for which a slot (if any) has already been acquired.
+    fn push(&self, value: T) -> Result<(), SendError<T>> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.rx_closed {
+            return Err(SendError(value));
+        }
+
+        state.queue.push_back(value);
+
+        if let Some(waker) = state.rx_waker.take() {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().rx_closed
+    }
+
+    fn add_sender(&self) {
+        self.state.lock().unwrap().senders += 1;
+    }
+
+    fn drop_sender(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        state.senders -= 1;
+
+        if state.senders == 0 {
+            if let Some(waker) = state.rx_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.queue.pop_front() {
+            Some(value) => {
+                drop(state);
+                self.release_slot();
+                Ok(value)
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.queue.pop_front() {
+            Some(value) => {
+                drop(state);
+                self.release_slot();
+                Poll::Ready(Some(value))
+            }
+            None if state.senders == 0 => Poll::Ready(None),
+            None => {
+                state.rx_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn release_slot(&self) {
+        if let Some(slots) = &self.slots {
+            slots.add_permits(1);
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().rx_closed = true;
+
+        if let Some(slots) = &self.slots {
+            slots.close();
+        }
+    }
+}
+
+/// The sending half of a bounded channel, created by [channel].
+///
+/// Senders can be cloned to send from multiple tasks.
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The receiving half of a bounded channel, created by [channel].
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The sending half of an unbounded channel, created by
+/// [unbounded_channel].
+///
+/// Senders can be cloned to send from multiple tasks.
+pub struct UnboundedSender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The receiving half of an unbounded channel, created by
+/// [unbounded_channel].
+pub struct UnboundedReceiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The error returned when sending on a closed channel. The value that could
+/// not be sent is handed back to the caller.
+#[derive(PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// The error returned by [Sender::try_send].
+#[derive(PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is full.
+    Full(T),
+    /// The receiver has been dropped or closed.
+    Closed(T),
+}
+
+/// The error returned by [Receiver::try_recv] and
+/// [UnboundedReceiver::try_recv].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The channel is currently empty.
+    Empty,
+    /// The channel is empty and all senders have been dropped.
+    Disconnected,
+}
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> Display for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+impl<T> Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("Full(..)"),
+            TrySendError::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> Display for TrySendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "channel full"),
+            TrySendError::Closed(_) => write!(f, "channel closed"),
+        }
+    }
+}
+
+impl<T> std::error::Error for TrySendError<T> {}
+
+impl Display for TryRecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "channel empty"),
+            TryRecvError::Disconnected => write!(f, "channel disconnected"),
+        }
+    }
+}
+
+impl std::error::Error for TryRecvError {}
+
+/// Create a bounded channel which holds at most `capacity` values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "mpsc channel capacity must be non-zero");
+
+    let chan = Chan::new(Some(Semaphore::new(capacity)));
+
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// Create an unbounded channel.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+    let chan = Chan::new(None);
+
+    (
+        UnboundedSender { chan: chan.clone() },
+        UnboundedReceiver { chan },
+    )
+}
+
+impl<T> Sender<T> {
+    /// Send `value`, waiting for space in the channel if it is full.
+    ///
+    /// Returns the value in a [SendError] if the receiver has been dropped or
+    /// closed.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let slots = self.chan.slots.as_ref().unwrap();
+
+        match slots.acquire(1).await {
+            Ok(permit) => permit.forget(),
+            Err(_) => return Err(SendError(value)),
+        }
+
+        self.chan.push(value)
+    }
+
+    /// Attempt to send `value` without waiting.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let slots = self.chan.slots.as_ref().unwrap();
+
+        match slots.try_acquire(1) {
+            Ok(permit) => permit.forget(),
+            Err(TryAcquireError::NoPermits) => return Err(TrySendError::Full(value)),
+            Err(TryAcquireError::Closed) => return Err(TrySendError::Closed(value)),
+        }
+
+        self.chan
+            .push(value)
+            .map_err(|SendError(value)| TrySendError::Closed(value))
+    }
+
+    /// Returns `true` if the receiver has been dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.chan.is_closed()
+    }
+}
+
+impl<T> UnboundedSender<T> {
+    /// Send `value` without waiting.
+    ///
+    /// Returns the value in a [SendError] if the receiver has been dropped or
+    /// closed.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.chan.push(value)
+    }
+
+    /// Returns `true` if the receiver has been dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.chan.is_closed()
+    }
+}
+
+macro_rules! impl_sender {
+    ($ty:ident) => {
+        impl<T> Clone for $ty<T> {
+            fn clone(&self) -> Self {
+                self.chan.add_sender();
+
+                Self {
+                    chan: self.chan.clone(),
+                }
+            }
+        }
+
+        impl<T> Drop for $ty<T> {
+            fn drop(&mut self) {
+                self.chan.drop_sender();
+            }
+        }
+
+        impl<T> Debug for $ty<T> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($ty)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+macro_rules! impl_receiver {
+    ($ty:ident) => {
+        impl<T> $ty<T> {
+            /// Receive the next value, waiting for one to be sent if the
+            /// channel is empty.
+            ///
+            /// Returns `None` once the channel is empty and all senders have
+            /// been dropped.
+            pub async fn recv(&mut self) -> Option<T> {
+                poll_fn(|cx| self.chan.poll_recv(cx)).await
+            }
+
+            /// Attempt to receive the next value without waiting.
+            pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+                self.chan.try_recv()
+            }
+
+            /// Poll to receive the next value. See the `recv` method.
+            pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+                self.chan.poll_recv(cx)
+            }
+
+            /// Close the channel, causing all further sends to fail.
+            ///
+            /// Values that have already been sent can still be received.
+            pub fn close(&mut self) {
+                self.chan.close();
+            }
+        }
+
+        impl<T> Stream for $ty<T> {
+            type Item = T;
+
+            fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+                self.chan.poll_recv(cx)
+            }
+        }
+
+        impl<T> Drop for $ty<T> {
+            fn drop(&mut self) {
+                self.chan.close();
+            }
+        }
+
+        impl<T> Debug for $ty<T> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($ty)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+impl_sender!(Sender);
+impl_sender!(UnboundedSender);
+impl_receiver!(Receiver);
+impl_receiver!(UnboundedReceiver);
+
+#[cfg(test)]
+mod tests {
+    use super::{channel, unbounded_channel, TryRecvError, TrySendError};
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{thread, time::Duration};
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn bounded_backpressure() {
+        let (tx, rx) = channel(2);
+
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+
+        Executor::block_on(async move {
+            let task = Executor::spawn(async move {
+                tx.send(3).await.unwrap();
+                tx.send(4).await.unwrap();
+            });
+
+            Timer::sleep(Duration::from_millis(10)).unwrap().await;
+
+            assert_eq!(rx.collect::<Vec<_>>().await, [1, 2, 3, 4]);
+            task.await;
+        });
+    }
+
+    #[test]
+    fn unbounded() {
+        let (tx, mut rx) = unbounded_channel();
+
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+
+        assert_eq!(rx.try_recv(), Ok(0));
+        drop(tx);
+
+        Executor::block_on(async move {
+            let mut n = 1;
+            while let Some(i) = rx.recv().await {
+                assert_eq!(i, n);
+                n += 1;
+            }
+            assert_eq!(n, 100);
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        });
+    }
+
+    #[test]
+    fn receiver_dropped() {
+        let (tx, rx) = channel(1);
+
+        tx.try_send(1).unwrap();
+
+        Executor::block_on(async move {
+            let task = Executor::spawn(async move { tx.send(2).await.map_err(|e| e.0) });
+
+            Timer::sleep(Duration::from_millis(10)).unwrap().await;
+            drop(rx);
+
+            assert_eq!(task.await, Err(2));
+        });
+
+        let (tx, mut rx) = unbounded_channel();
+        rx.close();
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1).map_err(|e| e.0), Err(1));
+    }
+
+    #[test]
+    fn cross_thread() {
+        let (tx, mut rx) = channel(1);
+        let (done_tx, mut done_rx) = unbounded_channel();
+
+        let t1 = thread::spawn(move || {
+            Executor::block_on(async move {
+                for i in 0..50 {
+                    tx.send(i).await.unwrap();
+                }
+                drop(tx);
+                done_rx.recv().await
+            })
+        });
+
+        let t2 = thread::spawn(move || {
+            Executor::block_on(async move {
+                let mut sum = 0;
+                while let Some(i) = rx.recv().await {
+                    sum += i;
+                }
+                done_tx.send(sum).unwrap();
+            })
+        });
+
+        t2.join().unwrap();
+        assert_eq!(t1.join().unwrap(), Some((0..50).sum()));
+    }
+}
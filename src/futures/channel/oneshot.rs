@@ -0,0 +1,286 @@
+//! ### One-shot Channels
+//!
+//! A one-shot channel sends exactly one value from a [Sender] to a
+//! [Receiver]. The receiver is itself a future which resolves to the value, or
+//! to a [RecvError] if the sender was dropped without sending anything.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::channel::oneshot;
+//!
+//! Executor::block_on(async {
+//!     let (tx, rx) = oneshot::channel();
+//!
+//!     Executor::spawn(async move {
+//!         tx.send(42).unwrap();
+//!     });
+//!
+//!     assert_eq!(rx.await, Ok(42));
+//! });
+//! ```
+use std::{
+    fmt::{Debug, Display},
+    future::{poll_fn, Future},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+struct Inner<T> {
+    value: Option<T>,
+    /// Set once the sender has either sent a value or been dropped.
+    complete: bool,
+    rx_closed: bool,
+    rx_waker: Option<Waker>,
+    tx_waker: Option<Waker>,
+}
+
+/// The sending half of a one-shot channel.
+pub struct Sender<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+/// The receiving half of a one-shot channel.
+///
+/// `.await` the receiver to obtain the value.
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+/// The error returned by a [Receiver] when the [Sender] was dropped without
+/// sending a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+/// The error returned by [Receiver::try_recv].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// No value has been sent yet.
+    Empty,
+    /// The sender was dropped without sending a value, or the value has
+    /// already been received.
+    Closed,
+}
+
+impl Display for RecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+impl Display for TryRecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "channel empty"),
+            TryRecvError::Closed => write!(f, "channel closed"),
+        }
+    }
+}
+
+impl std::error::Error for TryRecvError {}
+
+/// Create a new one-shot channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        value: None,
+        complete: false,
+        rx_closed: false,
+        rx_waker: None,
+        tx_waker: None,
+    }));
+
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner },
+    )
+}
+
+impl<T> Sender<T> {
+    /// Send `value` to the receiver.
+    ///
+    /// If the receiver has been dropped or closed, the value is handed back
+    /// in the `Err` variant.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.rx_closed {
+            return Err(value);
+        }
+
+        inner.value = Some(value);
+        inner.complete = true;
+
+        if let Some(waker) = inner.rx_waker.take() {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the receiver has been dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().rx_closed
+    }
+
+    /// Wait for the receiver to be dropped or closed.
+    ///
+    /// This is useful for abandoning a computation whose result is no longer
+    /// wanted.
+    pub async fn closed(&self) {
+        poll_fn(|cx| {
+            let mut inner = self.inner.lock().unwrap();
+
+            if inner.rx_closed {
+                return Poll::Ready(());
+            }
+
+            inner.tx_waker = Some(cx.waker().clone());
+
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.complete {
+            return;
+        }
+
+        inner.complete = true;
+
+        if let Some(waker) = inner.rx_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Attempt to receive the value without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        match inner.value.take() {
+            Some(value) => Ok(value),
+            None if inner.complete => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Close the channel, preventing the sender from sending a value.
+    ///
+    /// A value that was sent before the channel was closed can still be
+    /// received.
+    pub fn close(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.rx_closed = true;
+
+        if let Some(waker) = inner.tx_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.inner.lock().unwrap();
+
+        match inner.value.take() {
+            Some(value) => Poll::Ready(Ok(value)),
+            None if inner.complete => Poll::Ready(Err(RecvError)),
+            None => {
+                inner.rx_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{channel, RecvError, TryRecvError};
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn send_recv() {
+        let (tx, mut rx) = channel();
+
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn sender_dropped() {
+        let (tx, rx) = channel::<()>();
+
+        Executor::block_on(async move {
+            Executor::spawn(async move {
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                drop(tx);
+            });
+
+            assert_eq!(rx.await, Err(RecvError));
+        });
+    }
+
+    #[test]
+    fn receiver_closed() {
+        let (tx, rx) = channel();
+
+        Executor::block_on(async move {
+            Executor::spawn(async move {
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                drop(rx);
+            });
+
+            tx.closed().await;
+            assert!(tx.is_closed());
+            assert_eq!(tx.send(1), Err(1));
+        });
+    }
+
+    #[test]
+    fn cross_thread() {
+        let (tx, rx) = channel();
+
+        let t1 = thread::spawn(move || Executor::block_on(rx));
+
+        thread::sleep(Duration::from_millis(10));
+        tx.send(String::from("hello")).unwrap();
+
+        assert_eq!(t1.join().unwrap().unwrap(), "hello");
+    }
+}
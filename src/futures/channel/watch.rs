@@ -0,0 +1,433 @@
+//! ### Watch Channels
+//!
+//! A watch channel holds a single value which the [Sender] can update and any
+//! number of [Receiver]s can observe. Receivers only ever see the latest
+//! value; intermediate values may be skipped. This makes watch channels a
+//! good fit for configuration that may change at runtime, or for signalling
+//! a shutdown to many tasks at once.
+//!
+//! Each receiver tracks whether it has seen the current value.
+//! [Receiver::changed] waits until a new value is sent and marks it as seen;
+//! the value can then be inspected with [Receiver::borrow], or cloned out with
+//! [Receiver::get].
+//!
+//! **Note:** a [Ref] returned by `borrow` holds a read lock on the value.
+//! Sending a value while a `Ref` is alive on the same thread, for example
+//! `let v = rx.borrow(); tx.send(1)`, deadlocks. Keep `Ref`s short-lived,
+//! or use `get` when the value is cheap to clone.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::channel::watch;
+//!
+//! Executor::block_on(async {
+//!     let (tx, mut rx) = watch::channel("initial");
+//!
+//!     let task = Executor::spawn(async move {
+//!         rx.changed().await.unwrap();
+//!         *rx.borrow()
+//!     });
+//!
+//!     tx.send("updated").unwrap();
+//!
+//!     assert_eq!(task.await, "updated");
+//! });
+//! ```
+use std::{
+    fmt::{Debug, Display},
+    ops::Deref,
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard},
+};
+
+use crate::futures::notify::Notify;
+
+struct State {
+    version: u64,
+    tx_dropped: bool,
+    receivers: usize,
+}
+
+struct Shared<T> {
+    /// The value is locked separately from the rest of the state so that
+    /// holding a [Ref] doesn't block receivers from being cloned, dropped or
+    /// polled. When both are locked, `value` is locked first.
+    value: RwLock<T>,
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+/// The sending half of a watch channel.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// The receiving half of a watch channel.
+///
+/// Receivers can be cloned; the clone starts out having seen the same
+/// version of the value as the original.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    seen: u64,
+}
+
+/// A reference to the value held by a watch channel.
+///
+/// The value is read-locked while this reference is held, so it should not be
+/// held across an `.await`. Sending on the channel from the thread holding a
+/// `Ref` deadlocks.
+pub struct Ref<'a, T> {
+    value: RwLockReadGuard<'a, T>,
+}
+
+/// The error returned by [Sender::send] when there are no receivers. The
+/// value that could not be sent is handed back to the caller.
+#[derive(PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// The error returned by [Receiver::changed] when the sender has been
+/// dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> Display for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+impl Display for RecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+/// Create a watch channel holding `init`.
+///
+/// The initial value is considered seen by the receiver.
+pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        value: RwLock::new(init),
+        state: Mutex::new(State {
+            version: 0,
+            tx_dropped: false,
+            receivers: 1,
+        }),
+        notify: Notify::new(),
+    });
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared, seen: 0 },
+    )
+}
+
+impl<T> Sender<T> {
+    /// Replace the value and notify all receivers.
+    ///
+    /// Returns the value in a [SendError] if there are no receivers. This
+    /// waits for any outstanding [Ref]s to be dropped, so it deadlocks if one
+    /// is held by the calling thread.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut current = self.shared.value.write().unwrap();
+        let mut state = self.shared.state.lock().unwrap();
+
+        if state.receivers == 0 {
+            return Err(SendError(value));
+        }
+
+        *current = value;
+        state.version += 1;
+
+        drop(state);
+        drop(current);
+        self.shared.notify.notify_waiters();
+
+        Ok(())
+    }
+
+    /// Modify the value in place and notify all receivers, regardless of
+    /// whether there are any.
+    ///
+    /// Like [Sender::send], this deadlocks if the calling thread holds a
+    /// [Ref].
+    pub fn send_modify(&self, f: impl FnOnce(&mut T)) {
+        let mut current = self.shared.value.write().unwrap();
+
+        f(&mut current);
+        self.shared.state.lock().unwrap().version += 1;
+
+        drop(current);
+        self.shared.notify.notify_waiters();
+    }
+
+    /// Obtain a reference to the current value.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            value: self.shared.value.read().unwrap(),
+        }
+    }
+
+    /// Return a clone of the current value.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.borrow().clone()
+    }
+
+    /// Create a new receiver which has seen the current value.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.receivers += 1;
+
+        Receiver {
+            shared: self.shared.clone(),
+            seen: state.version,
+        }
+    }
+
+    /// The number of receivers of the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.state.lock().unwrap().receivers
+    }
+
+    /// Returns `true` if all receivers have been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.receiver_count() == 0
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().tx_dropped = true;
+        self.shared.notify.notify_waiters();
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Obtain a reference to the current value without marking it as seen.
+    ///
+    /// The sender can't update the value until the returned [Ref] is dropped.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            value: self.shared.value.read().unwrap(),
+        }
+    }
+
+    /// Obtain a reference to the current value and mark it as seen.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        let value = self.shared.value.read().unwrap();
+
+        self.seen = self.shared.state.lock().unwrap().version;
+
+        Ref { value }
+    }
+
+    /// Return a clone of the current value without marking it as seen.
+    ///
+    /// Unlike [Receiver::borrow], no lock is held once this returns.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.borrow().clone()
+    }
+
+    /// Returns `true` if a value has been sent that this receiver has not
+    /// yet seen.
+    ///
+    /// Fails with [RecvError] if the sender has been dropped.
+    pub fn has_changed(&self) -> Result<bool, RecvError> {
+        let state = self.shared.state.lock().unwrap();
+
+        if state.tx_dropped {
+            return Err(RecvError);
+        }
+
+        Ok(state.version != self.seen)
+    }
+
+    /// Wait for a value that this receiver has not yet seen and mark it as
+    /// seen.
+    ///
+    /// Fails with [RecvError] if the sender is dropped before a new value is
+    /// sent.
+    pub async fn changed(&mut self) -> Result<(), RecvError> {
+        loop {
+            // Register interest before checking the version so that a value
+            // sent in between isn't missed.
+            let notified = self.shared.notify.notified();
+
+            {
+                let state = self.shared.state.lock().unwrap();
+
+                if state.version != self.seen {
+                    self.seen = state.version;
+                    return Ok(());
+                }
+
+                if state.tx_dropped {
+                    return Err(RecvError);
+                }
+            }
+
+            notified.await;
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().receivers += 1;
+
+        Self {
+            shared: self.shared.clone(),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().receivers -= 1;
+    }
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{channel, RecvError};
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn latest_value_wins() {
+        let (tx, mut rx) = channel(0);
+
+        assert_eq!(rx.has_changed(), Ok(false));
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx.has_changed(), Ok(true));
+        assert_eq!(*rx.borrow_and_update(), 2);
+        assert_eq!(rx.has_changed(), Ok(false));
+
+        tx.send_modify(|v| *v += 1);
+        assert_eq!(*tx.borrow(), 3);
+
+        drop(tx);
+        assert_eq!(rx.has_changed(), Err(RecvError));
+        assert_eq!(*rx.borrow(), 3);
+    }
+
+    #[test]
+    fn ref_does_not_block_receivers() {
+        let (tx, rx) = channel(String::from("a"));
+
+        let v = rx.borrow();
+        let rx2 = rx.clone();
+        assert_eq!(rx2.has_changed(), Ok(false));
+        drop(rx2);
+        assert_eq!(*v, "a");
+        drop(v);
+
+        let v = rx.get();
+        tx.send(String::from("b")).unwrap();
+        assert_eq!(v, "a");
+        assert_eq!(rx.get(), "b");
+        assert_eq!(tx.get(), "b");
+    }
+
+    #[test]
+    fn changed_wakes_all_receivers() {
+        let (tx, rx) = channel(false);
+
+        Executor::block_on(async move {
+            let tasks: Vec<_> = (0..3)
+                .map(|_| {
+                    let mut rx = rx.clone();
+                    Executor::spawn(async move {
+                        while !*rx.borrow_and_update() {
+                            rx.changed().await.unwrap();
+                        }
+                    })
+                })
+                .collect();
+
+            Timer::sleep(Duration::from_millis(10)).unwrap().await;
+            tx.send(true).unwrap();
+
+            for task in tasks {
+                task.await;
+            }
+        });
+    }
+
+    #[test]
+    fn sender_dropped() {
+        let (tx, mut rx) = channel(());
+
+        Executor::block_on(async move {
+            Executor::spawn(async move {
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                drop(tx);
+            });
+
+            assert_eq!(rx.changed().await, Err(RecvError));
+        });
+    }
+
+    #[test]
+    fn cross_thread() {
+        let (tx, mut rx) = channel(0);
+
+        let t1 = thread::spawn(move || {
+            Executor::block_on(async move {
+                while *rx.borrow_and_update() < 10 {
+                    rx.changed().await.unwrap();
+                }
+            })
+        });
+
+        for i in 1..=10 {
+            thread::sleep(Duration::from_millis(2));
+            tx.send(i).unwrap();
+        }
+
+        t1.join().unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+//! ### Async Condition Variables
+//!
+//! A [Condvar] lets a task holding a [Mutex](super::mutex::Mutex) atomically
+//! release the lock and wait until another task signals that the protected
+//! state has changed. Once woken, the lock is re-acquired before the wait
+//! completes. As with `std::sync::Condvar`, wakeups may be spurious, so the
+//! condition should be re-checked after each wait; [Condvar::wait_while] does
+//! this for you.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::condvar::Condvar;
+//! use trale::futures::mutex::Mutex;
+//! use std::sync::Arc;
+//!
+//! let pair = Arc::new((Mutex::new(false).unwrap(), Condvar::new()));
+//!
+//! {
+//!     let pair = pair.clone();
+//!     Executor::spawn(async move {
+//!         let (lock, cvar) = &*pair;
+//!         *lock.lock().await = true;
+//!         cvar.notify_one();
+//!     });
+//! }
+//!
+//! Executor::block_on(async move {
+//!     let (lock, cvar) = &*pair;
+//!     let ready = cvar.wait_while(lock.lock().await, |ready| !*ready).await;
+//!     assert!(*ready);
+//! });
+//! ```
+use std::{
+    future::{poll_fn, Future},
+    pin::{pin, Pin},
+    sync::Mutex,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use super::{mutex::LockGuard, timer::Timer, waiters::WaitList};
+
+/// An async-aware condition variable.
+///
+/// See the [module-level documentation](self) for more information.
+pub struct Condvar {
+    /// Each waiter records whether it was woken by [Condvar::notify_one], in
+    /// which case it must pass the notification on if dropped.
+    waiters: Mutex<WaitList<bool>>,
+}
+
+/// Whether a [Condvar::wait_timeout] returned because the timeout elapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait timed out rather than being notified.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+fn notify_one(waiters: &mut WaitList<bool>) {
+    if let Some(one) = waiters.front_mut() {
+        *one = true;
+        waiters.notify_front();
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Condvar {
+    /// Create a new condition variable.
+    pub const fn new() -> Self {
+        Self {
+            waiters: Mutex::new(WaitList::new()),
+        }
+    }
+
+    /// Release the lock held by `guard` and wait to be notified, re-acquiring
+    /// the lock before returning.
+    pub async fn wait<'a, T>(&self, guard: LockGuard<'a, T>) -> LockGuard<'a, T> {
+        let mtx = LockGuard::mutex(&guard);
+
+        Wait {
+            cv: self,
+            guard: Some(guard),
+            key: None,
+        }
+        .await;
+
+        mtx.lock().await
+    }
+
+    /// Wait until `condition` returns `false`.
+    ///
+    /// The condition is checked with the lock held before the first wait and
+    /// after every wakeup.
+    pub async fn wait_while<'a, T>(
+        &self,
+        mut guard: LockGuard<'a, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> LockGuard<'a, T> {
+        while condition(&mut guard) {
+            guard = self.wait(guard).await;
+        }
+
+        guard
+    }
+
+    /// Like [Condvar::wait], but gives up waiting for a notification once
+    /// `timeout` has elapsed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying [Timer] can't be created.
+    pub async fn wait_timeout<'a, T>(
+        &self,
+        guard: LockGuard<'a, T>,
+        timeout: Duration,
+    ) -> (LockGuard<'a, T>, WaitTimeoutResult) {
+        let mtx = LockGuard::mutex(&guard);
+        let mut timer = pin!(Timer::sleep(timeout).unwrap());
+        let mut wait = pin!(Wait {
+            cv: self,
+            guard: Some(guard),
+            key: None,
+        });
+
+        let timed_out = poll_fn(|cx| {
+            if wait.as_mut().poll(cx).is_ready() {
+                Poll::Ready(false)
+            } else {
+                timer.as_mut().poll(cx).map(|_| true)
+            }
+        })
+        .await;
+
+        (mtx.lock().await, WaitTimeoutResult(timed_out))
+    }
+
+    /// Wake the longest-waiting task, if any.
+    pub fn notify_one(&self) {
+        notify_one(&mut self.waiters.lock().unwrap());
+    }
+
+    /// Wake all waiting tasks.
+    pub fn notify_all(&self) {
+        let mut waiters = self.waiters.lock().unwrap();
+
+        while waiters.notify_front() {}
+    }
+}
+
+/// A future which releases the lock once it has been queued and resolves
+/// when notified.
+struct Wait<'a, 'b, T> {
+    cv: &'a Condvar,
+    guard: Option<LockGuard<'b, T>>,
+    key: Option<usize>,
+}
+
+impl<T> Future for Wait<'_, '_, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut waiters = this.cv.waiters.lock().unwrap();
+
+        match this.key {
+            None => {
+                this.key = Some(waiters.push(false, cx.waker()));
+                drop(waiters);
+
+                // Only unlock once queued, so a notification sent as soon as
+                // the lock is released isn't missed.
+                this.guard = None;
+
+                Poll::Pending
+            }
+            Some(key) if waiters.is_notified(key) => {
+                waiters.remove(key);
+                this.key = None;
+
+                Poll::Ready(())
+            }
+            Some(key) => {
+                waiters.register(key, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Wait<'_, '_, T> {
+    fn drop(&mut self) {
+        let Some(key) = self.key else {
+            return;
+        };
+
+        let mut waiters = self.cv.waiters.lock().unwrap();
+
+        if let (true, true) = waiters.remove(key) {
+            notify_one(&mut waiters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Condvar;
+    use crate::{
+        futures::{mutex::Mutex, timer::Timer},
+        task::Executor,
+    };
+    use std::{
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn wait_while() {
+        let pair = Arc::new((Mutex::new(0).unwrap(), Condvar::new()));
+
+        Executor::block_on({
+            let pair = pair.clone();
+            async move {
+                let tasks: Vec<_> = (0..3)
+                    .map(|_| {
+                        let pair = pair.clone();
+                        Executor::spawn(async move {
+                            let (lock, cvar) = &*pair;
+                            let mut n = cvar.wait_while(lock.lock().await, |n| *n == 0).await;
+                            *n += 1;
+                        })
+                    })
+                    .collect();
+
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+
+                let (lock, cvar) = &*pair;
+                *lock.lock().await = 1;
+                cvar.notify_all();
+
+                for task in tasks {
+                    task.await;
+                }
+
+                assert_eq!(*lock.lock().await, 4);
+            }
+        });
+    }
+
+    #[test]
+    fn wait_timeout() {
+        let pair = Arc::new((Mutex::new(()).unwrap(), Condvar::new()));
+
+        Executor::block_on(async move {
+            let (lock, cvar) = &*pair;
+
+            let before = Instant::now();
+            let (guard, res) = cvar
+                .wait_timeout(lock.lock().await, Duration::from_millis(20))
+                .await;
+            assert!(res.timed_out());
+            assert!(before.elapsed() >= Duration::from_millis(20));
+            drop(guard);
+
+            let notifier = {
+                let pair = pair.clone();
+                Executor::spawn(async move {
+                    let _guard = pair.0.lock().await;
+                    pair.1.notify_one();
+                })
+            };
+
+            let (_guard, res) = cvar
+                .wait_timeout(lock.lock().await, Duration::from_secs(10))
+                .await;
+            assert!(!res.timed_out());
+
+            notifier.await;
+        });
+    }
+
+    #[test]
+    fn cross_thread() {
+        let pair = Arc::new((Mutex::new(false).unwrap(), Condvar::new()));
+
+        let t1 = {
+            let pair = pair.clone();
+            thread::spawn(move || {
+                Executor::block_on(async move {
+                    let (lock, cvar) = &*pair;
+                    let _ready = cvar.wait_while(lock.lock().await, |r| !*r).await;
+                })
+            })
+        };
+
+        thread::sleep(Duration::from_millis(10));
+        *pair.0.blocking_lock() = true;
+        pair.1.notify_one();
+
+        t1.join().unwrap();
+    }
+}
@@ -1,9 +1,14 @@
 //! Async event synchronisation.
 //!
-//! This module implements an event synchroniser between tasks. It is backed by
-//! the Linux kernel's
-//! [eventfd](https://man7.org/linux/man-pages/man2/eventfd.2.html). It allows
-//! one task to inform another that an event has taken place.
+//! This module implements an event synchroniser between tasks. It allows one
+//! task to inform another that an event has taken place.
+//!
+//! On kernels which support `IORING_OP_FUTEX_WAIT` (Linux 6.7 and later),
+//! events are counted in a plain atomic integer and waiting is an io_uring
+//! futex wait, so no file descriptor is needed and notifying an event that
+//! nobody is waiting on doesn't make a system call. On older kernels events
+//! fall back to the Linux kernel's
+//! [eventfd](https://man7.org/linux/man-pages/man2/eventfd.2.html).
 //!
 //! # Example
 //!
@@ -76,26 +81,69 @@ use std::{
     io::{ErrorKind, Result},
     os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
     pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
 use crate::reactor::{Reactor, ReactorIo};
 
+use super::futex;
+
+/// The state of a futex-backed event.
+struct FutexEvent {
+    /// The number of pending events; this is the futex word.
+    count: AtomicU32,
+    /// The number of waiters which may be sleeping on `count`.
+    waiters: AtomicU32,
+}
+
+impl FutexEvent {
+    fn try_take(&self) -> bool {
+        self.count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |c| c.checked_sub(1))
+            .is_ok()
+    }
+
+    fn wake_one(&self) -> Result<()> {
+        if self.waiters.load(Ordering::SeqCst) > 0 {
+            futex::wake(&self.count, 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+enum Inner {
+    Futex(Arc<FutexEvent>),
+    Fd(OwnedFd),
+}
+
+enum WaiterInner<'a> {
+    Futex {
+        evt: &'a FutexEvent,
+        registered: bool,
+    },
+    Fd(BorrowedFd<'a>),
+}
+
 /// Async event signaller
 ///
 /// Allows events to be signaled between async tasks. Use [Event::new] to create an
 /// event object, await the [Event::wait] future to suspend execution until an event
 /// arrives and [Event::notify_one] to send an event.
 pub struct Event {
-    inner: OwnedFd,
+    inner: Inner,
 }
 
 /// Event wait future
 ///
 /// A future that will wait for the assoicated Event to become ready. See
 /// [Event::wait()].
-pub struct EventWaiter<'fd> {
-    inner: BorrowedFd<'fd>,
+pub struct EventWaiter<'a> {
+    inner: WaiterInner<'a>,
     io: ReactorIo,
     wait_buf: [u8; std::mem::size_of::<u64>()],
 }
@@ -103,10 +151,28 @@ pub struct EventWaiter<'fd> {
 impl Event {
     /// Construct a new event object.
     ///
-    /// This function calls the `eventfd()` function and returns the wrapped
+    /// This uses a futex if the kernel supports waiting on one via io_uring,
+    /// otherwise it calls the `eventfd()` function and returns the wrapped
     /// file descriptor in an Event object. If the `eventfd()` function fails
     /// this function will return `Err` with an associated error object.
     pub fn new() -> Result<Self> {
+        if futex::is_supported() {
+            Ok(Self::new_futex())
+        } else {
+            Self::new_eventfd()
+        }
+    }
+
+    fn new_futex() -> Self {
+        Self {
+            inner: Inner::Futex(Arc::new(FutexEvent {
+                count: AtomicU32::new(0),
+                waiters: AtomicU32::new(0),
+            })),
+        }
+    }
+
+    fn new_eventfd() -> Result<Self> {
         let fd = unsafe { eventfd(0, EFD_NONBLOCK | EFD_SEMAPHORE) };
 
         if fd == -1 {
@@ -114,7 +180,7 @@ impl Event {
         }
 
         Ok(Self {
-            inner: unsafe { OwnedFd::from_raw_fd(fd) },
+            inner: Inner::Fd(unsafe { OwnedFd::from_raw_fd(fd) }),
         })
     }
 
@@ -126,10 +192,18 @@ impl Event {
     /// latched such that any subsequent awaits on [Event::wait] will *not*
     /// suspend execution, but will poll as `Ready`.
     pub fn notify_one(&self) -> Result<()> {
+        let fd = match &self.inner {
+            Inner::Futex(evt) => {
+                evt.count.fetch_add(1, Ordering::SeqCst);
+                return evt.wake_one();
+            }
+            Inner::Fd(fd) => fd,
+        };
+
         let buffer = 1_u64.to_ne_bytes();
         let ret = unsafe {
             libc::write(
-                self.inner.as_raw_fd(),
+                fd.as_raw_fd(),
                 buffer.as_ptr() as *const c_void,
                 buffer.len(),
             )
@@ -157,8 +231,16 @@ impl Event {
     /// called, `.await`ing on the return from this function will return
     /// `Ready`.
     pub fn wait(&mut self) -> EventWaiter<'_> {
+        let inner = match &self.inner {
+            Inner::Futex(evt) => WaiterInner::Futex {
+                evt,
+                registered: false,
+            },
+            Inner::Fd(fd) => WaiterInner::Fd(fd.as_fd()),
+        };
+
         EventWaiter {
-            inner: self.inner.as_fd(),
+            inner,
             io: Reactor::new_io(),
             wait_buf: [0; std::mem::size_of::<u64>()],
         }
@@ -167,9 +249,12 @@ impl Event {
 
 impl Clone for Event {
     fn clone(&self) -> Self {
-        Self {
-            inner: self.inner.try_clone().unwrap(),
-        }
+        let inner = match &self.inner {
+            Inner::Futex(evt) => Inner::Futex(evt.clone()),
+            Inner::Fd(fd) => Inner::Fd(fd.try_clone().unwrap()),
+        };
+
+        Self { inner }
     }
 }
 
@@ -179,33 +264,102 @@ impl Future for EventWaiter<'_> {
     fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = unsafe { self.get_unchecked_mut() };
 
-        this.io
-            .submit_or_get_result(|| {
-                (
-                    opcode::Read::new(
-                        types::Fd(this.inner.as_raw_fd()),
-                        this.wait_buf.as_mut_ptr(),
-                        this.wait_buf.len() as _,
-                    )
-                    .build(),
-                    ctx.waker().clone(),
-                )
-            })
-            .map(|x| x.map(|_| ()))
+        let (evt, registered) = match &mut this.inner {
+            WaiterInner::Futex { evt, registered } => (*evt, registered),
+            WaiterInner::Fd(fd) => {
+                let fd = fd.as_raw_fd();
+                let buf = &mut this.wait_buf;
+
+                return this
+                    .io
+                    .submit_or_get_result(|| {
+                        (
+                            opcode::Read::new(types::Fd(fd), buf.as_mut_ptr(), buf.len() as _)
+                                .build(),
+                            ctx.waker().clone(),
+                        )
+                    })
+                    .map(|x| x.map(|_| ()));
+            }
+        };
+
+        loop {
+            if evt.try_take() {
+                if std::mem::take(registered) {
+                    evt.waiters.fetch_sub(1, Ordering::SeqCst);
+                }
+
+                return Poll::Ready(Ok(()));
+            }
+
+            // Announce ourselves before sleeping, then check for an event
+            // again so that a concurrent notify either sees us or we see it.
+            if !*registered {
+                evt.waiters.fetch_add(1, Ordering::SeqCst);
+                *registered = true;
+                continue;
+            }
+
+            match this
+                .io
+                .submit_or_get_result(|| (futex::wait_entry(&evt.count, 0), ctx.waker().clone()))
+            {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(e)) if futex::is_value_mismatch(&e) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            }
+
+            this.io = Reactor::new_io();
+        }
+    }
+}
+
+impl Drop for EventWaiter<'_> {
+    fn drop(&mut self) {
+        if let WaiterInner::Futex {
+            evt,
+            registered: true,
+        } = self.inner
+        {
+            evt.waiters.fetch_sub(1, Ordering::SeqCst);
+
+            // We may have been woken for an event that we will now never
+            // take, so pass the wakeup on.
+            if evt.count.load(Ordering::SeqCst) > 0 {
+                let _ = evt.wake_one();
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::thread;
+    use std::{future::Future, thread};
 
     use super::Event;
-    use crate::task::Executor;
+    use crate::{futures::futex, task::Executor};
+
+    fn backends() -> Vec<fn() -> Event> {
+        let mut backends: Vec<fn() -> Event> = vec![|| Event::new_eventfd().unwrap()];
+
+        if futex::is_supported() {
+            backends.push(Event::new_futex);
+        }
+
+        backends
+    }
 
     #[test]
     fn simple() {
-        Executor::block_on(async {
-            let evt = Event::new().unwrap();
+        for new in backends() {
+            simple_with(new);
+        }
+    }
+
+    fn simple_with(new: fn() -> Event) {
+        Executor::block_on(async move {
+            let evt = new();
 
             let task = {
                 let mut evt = evt.clone();
@@ -221,8 +375,14 @@ mod tests {
 
     #[test]
     fn multi_notifiers() {
-        Executor::block_on(async {
-            let evt = Event::new().unwrap();
+        for new in backends() {
+            multi_notifiers_with(new);
+        }
+    }
+
+    fn multi_notifiers_with(new: fn() -> Event) {
+        Executor::block_on(async move {
+            let evt = new();
 
             let task = {
                 let mut evt = evt.clone();
@@ -258,4 +418,38 @@ mod tests {
             task.await;
         });
     }
+
+    #[test]
+    fn dropped_waiter_passes_on_wakeup() {
+        for new in backends() {
+            Executor::block_on(async move {
+                let evt = new();
+
+                let tasks: Vec<_> = (0..2)
+                    .map(|_| {
+                        let mut evt = evt.clone();
+                        Executor::spawn(async move {
+                            evt.wait().await.unwrap();
+                        })
+                    })
+                    .collect();
+
+                {
+                    let mut evt = evt.clone();
+                    let mut waiter = Box::pin(evt.wait());
+                    let waker = std::task::Waker::noop();
+                    let _ = waiter
+                        .as_mut()
+                        .poll(&mut std::task::Context::from_waker(waker));
+                }
+
+                evt.notify_one().unwrap();
+                evt.notify_one().unwrap();
+
+                for task in tasks {
+                    task.await;
+                }
+            });
+        }
+    }
 }
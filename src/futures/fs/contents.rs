@@ -0,0 +1,223 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use crate::futures::read::AsyncRead;
+
+use super::{File, OpenOptions};
+
+/// How many bytes are read past the expected end of the file to check that it
+/// hasn't grown since its size was queried.
+const PROBE_LEN: usize = 32;
+
+/// How much the buffer grows by when reading a file that can't be read at an
+/// offset.
+const CHUNK_LEN: usize = 8 * 1024;
+
+/// Read the entire contents of the file at `path` into a vector.
+///
+/// The file's size is queried first so that the buffer can be allocated up
+/// front, rather than grown as the file is read. A file which doesn't report a
+/// size, or which grows while it is being read, is still read to the end.
+/// Files other than regular files, such as pipes and character devices, are
+/// read until they report end of file.
+pub fn read(path: impl AsRef<Path>) -> impl Future<Output = Result<Vec<u8>>> + 'static {
+    let open = File::open(path);
+
+    async move {
+        let mut file = open.await?;
+
+        match file.metadata().await {
+            Ok(meta) if !meta.is_file() => read_unpositioned(&mut file).await,
+            meta => read_to_end(&file, meta.map_or(0, |m| m.len() as usize)).await,
+        }
+    }
+}
+
+/// Read the entire contents of the file at `path` into a string.
+///
+/// This is [read] followed by a check that the contents are valid UTF-8.
+pub fn read_to_string(path: impl AsRef<Path>) -> impl Future<Output = Result<String>> + 'static {
+    let read = read(path);
+
+    async move {
+        String::from_utf8(read.await?)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
+    }
+}
+
+/// Write `contents` to the file at `path`, replacing its contents if it
+/// already exists and creating it otherwise.
+pub fn write<C: AsRef<[u8]>>(
+    path: impl AsRef<Path>,
+    contents: C,
+) -> impl Future<Output = Result<()>> {
+    let open = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path);
+
+    async move {
+        let file = open.await?;
+        write_all_at(&file, contents.as_ref(), 0).await
+    }
+}
+
+async fn read_to_end(file: &File, size: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0; size];
+    let mut len = 0;
+
+    loop {
+        if len == buf.len() {
+            let mut probe = [0; PROBE_LEN];
+            let n = file.read_at(&mut probe, len as u64).await?;
+
+            if n == 0 {
+                return Ok(buf);
+            }
+
+            buf.extend_from_slice(&probe[..n]);
+            len += n;
+            buf.resize(len + len.max(PROBE_LEN), 0);
+        }
+
+        match file.read_at(&mut buf[len..], len as u64).await? {
+            0 => {
+                buf.truncate(len);
+                return Ok(buf);
+            }
+            n => len += n,
+        }
+    }
+}
+
+/// Read from the file position to the end of the file, for files which can't
+/// be read at an offset.
+async fn read_unpositioned(file: &mut File) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    let mut len = 0;
+
+    loop {
+        if len == buf.len() {
+            buf.resize(len + len.max(CHUNK_LEN), 0);
+        }
+
+        match file.read(&mut buf[len..]).await? {
+            0 => {
+                buf.truncate(len);
+                return Ok(buf);
+            }
+            n => len += n,
+        }
+    }
+}
+
+pub(super) async fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> Result<()> {
+    while !buf.is_empty() {
+        match file.write_at(buf, offset).await? {
+            0 => {
+                return Err(Error::new(
+                    ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ))
+            }
+            n => {
+                buf = &buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use assert_fs::{
+        assert::PathAssert,
+        prelude::{FileWriteBin, FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use super::read_to_end;
+    use crate::{
+        futures::fs::{self, File},
+        task::Executor,
+    };
+
+    #[test]
+    fn read_and_write() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("This will be replaced").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            fs::write(&path, "Hello, world!").await.unwrap();
+            assert_eq!(fs::read(&path).await.unwrap(), b"Hello, world!");
+            assert_eq!(fs::read_to_string(&path).await.unwrap(), "Hello, world!");
+        });
+
+        child.assert("Hello, world!");
+    }
+
+    #[test]
+    fn read_unknown_size() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.bin");
+        let data: Vec<u8> = (0..10_000).map(|x| x as u8).collect();
+        child.write_binary(&data).unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let file = File::open(&path).await.unwrap();
+
+            // The file may be larger or smaller than it was expected to be.
+            for size in [0, 100, 10_000, 20_000] {
+                assert_eq!(read_to_end(&file, size).await.unwrap(), data);
+            }
+        });
+    }
+
+    #[test]
+    fn read_invalid_utf8() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.bin");
+        child.write_binary(&[0xff, 0xfe]).unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let err = fs::read_to_string(&path).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+            let err = fs::read(path.join("missing")).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotADirectory);
+        });
+    }
+
+    #[test]
+    fn read_unseekable() {
+        let dir = TempDir::new().unwrap();
+        let fifo = dir.child("fifo").to_path_buf();
+        let c_path = std::ffi::CString::new(fifo.as_os_str().as_encoded_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+        let data: Vec<u8> = (0..20_000).map(|x| x as u8).collect();
+
+        let writer = {
+            let (fifo, data) = (fifo.clone(), data.clone());
+            std::thread::spawn(move || std::fs::write(fifo, data).unwrap())
+        };
+
+        Executor::block_on(async move {
+            assert_eq!(fs::read(&fifo).await.unwrap(), data);
+            assert!(fs::read("/dev/null").await.unwrap().is_empty());
+        });
+
+        writer.join().unwrap();
+    }
+}
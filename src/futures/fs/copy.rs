@@ -0,0 +1,253 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use io_uring::{opcode, types};
+
+use crate::reactor::{Reactor, ReactorIo};
+
+use super::{contents::write_all_at, offload::offload, File, OpenOptions};
+
+/// How much data is moved through the pipe by each splice.
+const CHUNK_LEN: u32 = 64 * 1024;
+
+/// Copy the contents of the file at `src` to the file at `dst`, returning the
+/// number of bytes copied.
+///
+/// `dst` is created if it doesn't exist and truncated if it does, and its
+/// permission bits are set to those of `src`. The data is spliced from one
+/// file to the other through a pipe, so it never passes through userspace.
+/// On kernels without `IORING_OP_SPLICE` (before Linux 5.7) the data is read
+/// and written through a buffer instead.
+pub fn copy(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> impl Future<Output = Result<u64>> + 'static {
+    let src = File::open(src);
+    let dst = dst.as_ref().to_path_buf();
+
+    async move {
+        let src = src.await?;
+        let meta = src.metadata().await?;
+
+        if !meta.is_file() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the source path is not a regular file",
+            ));
+        }
+
+        let perm = meta.mode() & 0o7777;
+        let dst = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(perm)
+            .open(dst)
+            .await?;
+
+        // The umask applies to the mode a file is created with, and an
+        // existing file keeps its mode. io_uring has no opcode for
+        // `fchmod(2)`, so it is made on a helper thread.
+        let fd = dst.inner.try_clone()?;
+        offload(move || {
+            if unsafe { libc::fchmod(fd.as_raw_fd(), perm) } == -1 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        })
+        .await
+        .unwrap_or_else(|_| Err(Error::other("fchmod thread panicked")))?;
+
+        if Reactor::is_supported(opcode::Splice::CODE) {
+            splice_all(&src, &dst).await
+        } else {
+            copy_buffered(&src, &dst).await
+        }
+    }
+}
+
+async fn splice_all(src: &File, dst: &File) -> Result<u64> {
+    let (rx, tx) = pipe()?;
+    let mut copied = 0;
+
+    loop {
+        let mut n =
+            Splice::new(src.inner.as_fd(), copied as i64, tx.as_fd(), -1, CHUNK_LEN).await?;
+
+        if n == 0 {
+            return Ok(copied);
+        }
+
+        while n > 0 {
+            let written = Splice::new(rx.as_fd(), -1, dst.inner.as_fd(), copied as i64, n).await?;
+
+            if written == 0 {
+                return Err(Error::new(
+                    ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ));
+            }
+
+            n -= written;
+            copied += written as u64;
+        }
+    }
+}
+
+async fn copy_buffered(src: &File, dst: &File) -> Result<u64> {
+    let mut buf = vec![0; CHUNK_LEN as usize];
+    let mut copied = 0;
+
+    loop {
+        match src.read_at(&mut buf, copied).await? {
+            0 => return Ok(copied),
+            n => {
+                write_all_at(dst, &buf[..n], copied).await?;
+                copied += n as u64;
+            }
+        }
+    }
+}
+
+fn pipe() -> Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+/// Move up to `len` bytes from `fd_in` to `fd_out`, like `splice(2)`. An
+/// offset of -1 must be used for the end which is a pipe.
+struct Splice<'a> {
+    fd_in: BorrowedFd<'a>,
+    off_in: i64,
+    fd_out: BorrowedFd<'a>,
+    off_out: i64,
+    len: u32,
+    io: ReactorIo,
+}
+
+impl<'a> Splice<'a> {
+    fn new(
+        fd_in: BorrowedFd<'a>,
+        off_in: i64,
+        fd_out: BorrowedFd<'a>,
+        off_out: i64,
+        len: u32,
+    ) -> Self {
+        Self {
+            fd_in,
+            off_in,
+            fd_out,
+            off_out,
+            len,
+            io: Reactor::new_io(),
+        }
+    }
+}
+
+impl Future for Splice<'_> {
+    type Output = Result<u32>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::Splice::new(
+                        types::Fd(this.fd_in.as_raw_fd()),
+                        this.off_in,
+                        types::Fd(this.fd_out.as_raw_fd()),
+                        this.off_out,
+                        this.len,
+                    )
+                    .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|n| n as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, os::unix::fs::PermissionsExt};
+
+    use assert_fs::{
+        prelude::{FileWriteBin, FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use super::copy_buffered;
+    use crate::{
+        futures::fs::{self, File, OpenOptions},
+        task::Executor,
+    };
+
+    #[test]
+    fn copy_file() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.child("src.bin");
+        let dst = dir.child("dst.bin");
+        let data: Vec<u8> = (0..200_000).map(|x| (x % 251) as u8).collect();
+        src.write_binary(&data).unwrap();
+        dst.write_str("This will be replaced").unwrap();
+        std::fs::set_permissions(src.path(), std::fs::Permissions::from_mode(0o640)).unwrap();
+        let (src_path, dst_path) = (src.to_path_buf(), dst.to_path_buf());
+
+        Executor::block_on(async move {
+            assert_eq!(fs::copy(&src_path, &dst_path).await.unwrap(), 200_000);
+        });
+
+        assert_eq!(std::fs::read(dst.path()).unwrap(), data);
+        let mode = std::fs::metadata(dst.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[test]
+    fn copy_buffered_fallback() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.child("src.bin");
+        let dst = dir.child("dst.bin");
+        let data: Vec<u8> = (0..100_000).map(|x| (x % 251) as u8).collect();
+        src.write_binary(&data).unwrap();
+        let (src_path, dst_path) = (src.to_path_buf(), dst.to_path_buf());
+
+        Executor::block_on(async move {
+            let src = File::open(&src_path).await.unwrap();
+            let dst = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .open(&dst_path)
+                .await
+                .unwrap();
+
+            assert_eq!(copy_buffered(&src, &dst).await.unwrap(), 100_000);
+        });
+
+        assert_eq!(std::fs::read(dst.path()).unwrap(), data);
+    }
+
+    #[test]
+    fn copy_not_a_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.to_path_buf();
+
+        Executor::block_on(async move {
+            let err = fs::copy(&path, path.join("dst")).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert!(!path.join("dst").exists());
+        });
+    }
+}
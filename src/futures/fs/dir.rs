@@ -0,0 +1,251 @@
+use std::{
+    future::Future,
+    io::Result,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use super::{cstr, FileOpen, Mkdir, OpenOptions, Rename, Statx, Unlink};
+
+/// An open directory.
+///
+/// Operations on a `Dir` take paths relative to the directory rather than to
+/// the current working directory, using the `*at` family of system calls with
+/// the directory's file descriptor. The directory can be renamed or replaced
+/// after it has been opened without affecting where those operations take
+/// place, which avoids the time-of-check to time-of-use races inherent to
+/// joining paths.
+///
+/// To also stop a path from escaping the directory through `..` or symbolic
+/// links, open files with [OpenOptions::resolve] set to
+/// `libc::RESOLVE_BENEATH`.
+///
+/// # Example
+///
+/// ```
+/// use trale::task::Executor;
+/// use trale::futures::fs::{Dir, OpenOptions};
+/// use trale::futures::write::AsyncWrite;
+///# use assert_fs::TempDir;
+///
+/// Executor::block_on(async {
+///#     let tmp = TempDir::new().unwrap();
+///#     let path = tmp.path();
+///     let dir = Dir::open(path).await?;
+///     dir.mkdir_at("logs", 0o755).await?;
+///
+///     let mut file = dir
+///         .open_at(
+///             "logs/out.txt",
+///             OpenOptions::new()
+///                 .write(true)
+///                 .create(true)
+///                 .resolve(libc::RESOLVE_BENEATH),
+///         )
+///         .await?;
+///
+///     file.write(b"sandboxed").await?;
+///#     Ok::<(), std::io::Error>(())
+/// });
+/// ```
+pub struct Dir {
+    inner: OwnedFd,
+}
+
+/// A future for opening a directory, returned by [Dir::open].
+pub struct DirOpen {
+    open: FileOpen<'static>,
+}
+
+impl Future for DirOpen {
+    type Output = Result<Dir>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let open = unsafe { self.map_unchecked_mut(|this| &mut this.open) };
+
+        open.poll(cx)
+            .map(|x| x.map(|file| Dir { inner: file.inner }))
+    }
+}
+
+impl Dir {
+    /// Attempt to open the directory at `path`.
+    pub fn open(path: impl AsRef<Path>) -> DirOpen {
+        let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC;
+
+        DirOpen {
+            open: FileOpen::new(libc::AT_FDCWD, cstr(path.as_ref()), Ok(flags), 0, 0),
+        }
+    }
+
+    /// Open the file at `path`, relative to this directory, with `options`.
+    pub fn open_at(&self, path: impl AsRef<Path>, options: &OpenOptions) -> FileOpen<'_> {
+        options.open_at(self.as_raw_fd(), path.as_ref())
+    }
+
+    /// Create a directory at `path`, relative to this directory, with the
+    /// permission bits `mode`.
+    pub fn mkdir_at(&self, path: impl AsRef<Path>, mode: u32) -> Mkdir<'_> {
+        Mkdir::new(self.as_raw_fd(), cstr(path.as_ref()), mode)
+    }
+
+    /// Remove the entry at `path`, relative to this directory.
+    ///
+    /// `flags` is passed to `unlinkat(2)`; pass `libc::AT_REMOVEDIR` to
+    /// remove an empty directory rather than a file.
+    pub fn unlink_at(&self, path: impl AsRef<Path>, flags: i32) -> Unlink<'_> {
+        Unlink::new(self.as_raw_fd(), cstr(path.as_ref()), flags)
+    }
+
+    /// Rename `from`, relative to this directory, to `to`, relative to
+    /// `to_dir`.
+    ///
+    /// `flags` accepts the same values as
+    /// [rename_with_flags](super::rename_with_flags).
+    pub fn rename_at<'a>(
+        &'a self,
+        from: impl AsRef<Path>,
+        to_dir: &'a Dir,
+        to: impl AsRef<Path>,
+        flags: u32,
+    ) -> Rename<'a> {
+        Rename::new(
+            self.as_raw_fd(),
+            cstr(from.as_ref()),
+            to_dir.as_raw_fd(),
+            cstr(to.as_ref()),
+            flags,
+        )
+    }
+
+    /// Query the metadata of `path`, relative to this directory.
+    ///
+    /// `flags` is passed to `statx(2)`; pass `libc::AT_SYMLINK_NOFOLLOW` to
+    /// query a symbolic link itself, or `libc::AT_EMPTY_PATH` with an empty
+    /// path to query the directory.
+    pub fn statx_at(&self, path: impl AsRef<Path>, flags: i32) -> Statx<'_> {
+        Statx::new(self.as_raw_fd(), cstr(path.as_ref()), flags)
+    }
+}
+
+impl AsFd for Dir {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+impl AsRawFd for Dir {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use assert_fs::{
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use super::Dir;
+    use crate::{
+        futures::{fs::OpenOptions, read::AsyncRead},
+        task::Executor,
+    };
+
+    #[test]
+    fn relative_operations() {
+        let tmp = TempDir::new().unwrap();
+        tmp.child("a.txt").write_str("data").unwrap();
+        let path = tmp.to_path_buf();
+
+        Executor::block_on(async move {
+            let dir = Dir::open(&path).await.unwrap();
+
+            dir.mkdir_at("sub", 0o700).await.unwrap();
+            assert!(dir.statx_at("sub", 0).await.unwrap().is_dir());
+            assert!(dir
+                .statx_at("", libc::AT_EMPTY_PATH)
+                .await
+                .unwrap()
+                .is_dir());
+
+            let sub = Dir::open(path.join("sub")).await.unwrap();
+            dir.rename_at("a.txt", &sub, "b.txt", 0).await.unwrap();
+
+            let mut f = sub
+                .open_at("b.txt", OpenOptions::new().read(true))
+                .await
+                .unwrap();
+            let mut buf = [0; 8];
+            let len = f.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], b"data");
+
+            let err = dir.unlink_at("sub", 0).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::IsADirectory);
+            sub.unlink_at("b.txt", 0).await.unwrap();
+            dir.unlink_at("sub", libc::AT_REMOVEDIR).await.unwrap();
+        });
+
+        assert!(!tmp.child("a.txt").path().exists());
+        assert!(!tmp.child("sub").path().exists());
+    }
+
+    #[test]
+    fn not_a_directory() {
+        let tmp = TempDir::new().unwrap();
+        let file = tmp.child("a.txt");
+        file.write_str("data").unwrap();
+        let path = file.to_path_buf();
+
+        Executor::block_on(async move {
+            assert!(Dir::open(&path).await.is_err());
+        });
+    }
+
+    #[test]
+    fn resolve_beneath() {
+        let tmp = TempDir::new().unwrap();
+        tmp.child("secret.txt").write_str("secret").unwrap();
+        let root = tmp.child("root");
+        std::fs::create_dir(root.path()).unwrap();
+        root.child("inside.txt").write_str("inside").unwrap();
+        std::os::unix::fs::symlink("../secret.txt", root.child("escape").path()).unwrap();
+        std::os::unix::fs::symlink("inside.txt", root.child("link").path()).unwrap();
+        let path = root.to_path_buf();
+
+        Executor::block_on(async move {
+            let dir = Dir::open(&path).await.unwrap();
+            let mut beneath = OpenOptions::new();
+            beneath.read(true).resolve(libc::RESOLVE_BENEATH);
+
+            assert!(dir.open_at("inside.txt", &beneath).await.is_ok());
+            assert!(dir.open_at("link", &beneath).await.is_ok());
+
+            for escape in ["../secret.txt", "escape"] {
+                let err = dir.open_at(escape, &beneath).await.err().unwrap();
+                assert_eq!(err.raw_os_error(), Some(libc::EXDEV));
+            }
+
+            // Without restrictions, the same paths escape the directory.
+            let plain = OpenOptions::new().read(true).clone();
+            assert!(dir.open_at("escape", &plain).await.is_ok());
+
+            let err = dir
+                .open_at(
+                    "link",
+                    OpenOptions::new()
+                        .read(true)
+                        .resolve(libc::RESOLVE_NO_SYMLINKS),
+                )
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.raw_os_error(), Some(libc::ELOOP));
+        });
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    future::Future,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use super::{cstr, metadata, Mkdir};
+
+/// A builder used to create directories in various manners.
+///
+/// This mirrors [std::fs::DirBuilder]. By default directories are created with
+/// mode `0o777` (before the umask is applied) and parent directories are not
+/// created.
+///
+/// # Example
+///
+/// ```
+/// use trale::task::Executor;
+/// use trale::futures::fs::DirBuilder;
+///# use assert_fs::TempDir;
+///
+/// Executor::block_on(async {
+///#     let dir = TempDir::new().unwrap();
+///#     let path = dir.join("a/b/c");
+///     DirBuilder::new()
+///         .recursive(true)
+///         .mode(0o750)
+///         .create(path)
+///         .await?;
+///#     Ok::<(), std::io::Error>(())
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct DirBuilder {
+    mode: u32,
+    recursive: bool,
+}
+
+impl Default for DirBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirBuilder {
+    /// Create a new set of options with default mode and non-recursive
+    /// creation.
+    pub fn new() -> Self {
+        Self {
+            mode: 0o777,
+            recursive: false,
+        }
+    }
+
+    /// Set the permission bits directories are created with.
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Create all missing parent directories as well.
+    ///
+    /// When set, it is not an error if the directory already exists.
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Return a future which creates the directory at `path` with these
+    /// options.
+    pub fn create(&self, path: impl AsRef<Path>) -> impl Future<Output = Result<()>> + 'static {
+        let path = path.as_ref().to_path_buf();
+        let builder = self.clone();
+
+        async move {
+            if builder.recursive {
+                builder.create_all(&path).await
+            } else {
+                builder.mkdir(&path).await
+            }
+        }
+    }
+
+    fn mkdir(&self, path: &Path) -> Mkdir<'static> {
+        Mkdir::new(libc::AT_FDCWD, cstr(path), self.mode)
+    }
+
+    /// Create `path`, succeeding if it already exists as a directory.
+    async fn mkdir_or_exists(&self, path: &Path) -> Result<()> {
+        match self.mkdir(path).await {
+            Err(_) if is_dir(path).await => Ok(()),
+            res => res,
+        }
+    }
+
+    async fn create_all(&self, path: &Path) -> Result<()> {
+        if path == Path::new("") {
+            return Ok(());
+        }
+
+        // Walk up until a directory can be created or is found to exist, then
+        // create the missing ones back down towards `path`.
+        let mut missing: Vec<PathBuf> = Vec::new();
+        let mut cur = path;
+
+        loop {
+            match self.mkdir(cur).await {
+                Ok(()) => break,
+                Err(e) if e.kind() == ErrorKind::NotFound => match cur.parent() {
+                    Some(parent) if parent != Path::new("") => {
+                        missing.push(cur.to_path_buf());
+                        cur = parent;
+                    }
+                    _ => return Err(e),
+                },
+                Err(_) if is_dir(cur).await => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        for dir in missing.iter().rev() {
+            self.mkdir_or_exists(dir).await?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn is_dir(path: &Path) -> bool {
+    metadata(path).await.is_ok_and(|m| m.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, os::unix::fs::PermissionsExt};
+
+    use assert_fs::{
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use super::DirBuilder;
+    use crate::{futures::fs::create_dir_all, task::Executor};
+
+    #[test]
+    fn create_all() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.join("a/b/c");
+
+        Executor::block_on({
+            let path = path.clone();
+            async move {
+                create_dir_all(&path).await.unwrap();
+                // Already existing is not an error.
+                create_dir_all(&path).await.unwrap();
+            }
+        });
+
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn non_recursive() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.join("a/b");
+
+        Executor::block_on(async move {
+            let err = DirBuilder::new().create(&path).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+        });
+    }
+
+    #[test]
+    fn with_mode() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.join("a");
+
+        Executor::block_on({
+            let path = path.clone();
+            async move { DirBuilder::new().mode(0o700).create(&path).await.unwrap() }
+        });
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    #[test]
+    fn file_in_the_way() {
+        let dir = TempDir::new().unwrap();
+        dir.child("a").write_str("data").unwrap();
+        let path = dir.join("a/b");
+
+        Executor::block_on(async move {
+            assert!(create_dir_all(&path).await.is_err());
+        });
+    }
+}
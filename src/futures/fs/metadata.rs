@@ -0,0 +1,400 @@
+use std::{
+    ffi::CString,
+    fmt::Debug,
+    fs::Permissions,
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    marker::{PhantomData, PhantomPinned},
+    os::{
+        fd::{BorrowedFd, RawFd},
+        unix::fs::PermissionsExt,
+    },
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use io_uring::{opcode, types};
+
+use crate::reactor::{Reactor, ReactorIo};
+
+/// The fields requested from `statx(2)`: everything `stat(2)` returns plus the
+/// birth time and mount id, if the filesystem provides them.
+const STATX_MASK: u32 = libc::STATX_BASIC_STATS | libc::STATX_BTIME | libc::STATX_MNT_ID;
+
+/// Metadata information about a file.
+///
+/// This is returned by [metadata](super::metadata),
+/// [symlink_metadata](super::symlink_metadata) and
+/// [File::metadata](super::File::metadata). Fields which the filesystem did not
+/// report are surfaced as an error or `None` by their accessor.
+#[derive(Clone)]
+pub struct Metadata {
+    stat: libc::statx,
+}
+
+/// The type of a file, as returned by [Metadata::file_type].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FileType {
+    mode: u32,
+}
+
+impl FileType {
+    /// Convert a `d_type` from a directory listing, returning `None` for
+    /// `DT_UNKNOWN`.
+    pub(super) fn from_d_type(d_type: u8) -> Option<Self> {
+        let mode = match d_type {
+            libc::DT_DIR => libc::S_IFDIR,
+            libc::DT_REG => libc::S_IFREG,
+            libc::DT_LNK => libc::S_IFLNK,
+            libc::DT_BLK => libc::S_IFBLK,
+            libc::DT_CHR => libc::S_IFCHR,
+            libc::DT_FIFO => libc::S_IFIFO,
+            libc::DT_SOCK => libc::S_IFSOCK,
+            _ => return None,
+        };
+
+        Some(Self { mode })
+    }
+
+    fn is(&self, kind: u32) -> bool {
+        self.mode & libc::S_IFMT == kind
+    }
+
+    /// Returns `true` if this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is(libc::S_IFDIR)
+    }
+
+    /// Returns `true` if this is a regular file.
+    pub fn is_file(&self) -> bool {
+        self.is(libc::S_IFREG)
+    }
+
+    /// Returns `true` if this is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.is(libc::S_IFLNK)
+    }
+
+    /// Returns `true` if this is a block device.
+    pub fn is_block_device(&self) -> bool {
+        self.is(libc::S_IFBLK)
+    }
+
+    /// Returns `true` if this is a character device.
+    pub fn is_char_device(&self) -> bool {
+        self.is(libc::S_IFCHR)
+    }
+
+    /// Returns `true` if this is a FIFO.
+    pub fn is_fifo(&self) -> bool {
+        self.is(libc::S_IFIFO)
+    }
+
+    /// Returns `true` if this is a socket.
+    pub fn is_socket(&self) -> bool {
+        self.is(libc::S_IFSOCK)
+    }
+}
+
+impl Metadata {
+    fn has(&self, field: u32) -> bool {
+        self.stat.stx_mask & field != 0
+    }
+
+    fn time(&self, field: u32, ts: &libc::statx_timestamp) -> Result<SystemTime> {
+        if !self.has(field) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "timestamp not available on this filesystem",
+            ));
+        }
+
+        let nanos = Duration::new(0, ts.tv_nsec);
+        let time = if ts.tv_sec >= 0 {
+            SystemTime::UNIX_EPOCH + Duration::from_secs(ts.tv_sec as u64) + nanos
+        } else {
+            SystemTime::UNIX_EPOCH - Duration::from_secs(ts.tv_sec.unsigned_abs()) + nanos
+        };
+
+        Ok(time)
+    }
+
+    /// Returns the type of this file.
+    pub fn file_type(&self) -> FileType {
+        FileType { mode: self.mode() }
+    }
+
+    /// Returns `true` if this metadata is for a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Returns `true` if this metadata is for a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    /// Returns `true` if this metadata is for a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.file_type().is_symlink()
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub fn len(&self) -> u64 {
+        self.stat.stx_size
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the permissions of the file.
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.mode())
+    }
+
+    /// Returns the raw `st_mode`, containing both the file type and the
+    /// permission bits.
+    pub fn mode(&self) -> u32 {
+        self.stat.stx_mode as u32
+    }
+
+    /// Returns the last modification time.
+    pub fn modified(&self) -> Result<SystemTime> {
+        self.time(libc::STATX_MTIME, &self.stat.stx_mtime)
+    }
+
+    /// Returns the last access time.
+    pub fn accessed(&self) -> Result<SystemTime> {
+        self.time(libc::STATX_ATIME, &self.stat.stx_atime)
+    }
+
+    /// Returns the last status change time.
+    pub fn changed(&self) -> Result<SystemTime> {
+        self.time(libc::STATX_CTIME, &self.stat.stx_ctime)
+    }
+
+    /// Returns the creation (birth) time.
+    ///
+    /// Not every filesystem records this, in which case an error of kind
+    /// [ErrorKind::Unsupported] is returned.
+    pub fn created(&self) -> Result<SystemTime> {
+        self.time(libc::STATX_BTIME, &self.stat.stx_btime)
+    }
+
+    /// Returns the inode number.
+    pub fn ino(&self) -> u64 {
+        self.stat.stx_ino
+    }
+
+    /// Returns the id of the device containing the file.
+    pub fn dev(&self) -> u64 {
+        libc::makedev(self.stat.stx_dev_major, self.stat.stx_dev_minor)
+    }
+
+    /// Returns the device id, if this file is a block or character device.
+    pub fn rdev(&self) -> u64 {
+        libc::makedev(self.stat.stx_rdev_major, self.stat.stx_rdev_minor)
+    }
+
+    /// Returns the number of hard links to the file.
+    pub fn nlink(&self) -> u64 {
+        self.stat.stx_nlink as u64
+    }
+
+    /// Returns the user id of the file's owner.
+    pub fn uid(&self) -> u32 {
+        self.stat.stx_uid
+    }
+
+    /// Returns the group id of the file's owner.
+    pub fn gid(&self) -> u32 {
+        self.stat.stx_gid
+    }
+
+    /// Returns the preferred block size for I/O.
+    pub fn blksize(&self) -> u64 {
+        self.stat.stx_blksize as u64
+    }
+
+    /// Returns the number of 512-byte blocks allocated to the file.
+    pub fn blocks(&self) -> u64 {
+        self.stat.stx_blocks
+    }
+
+    /// Returns the id of the mount containing the file, if the kernel
+    /// reported one (Linux 5.8 and later).
+    pub fn mount_id(&self) -> Option<u64> {
+        self.has(libc::STATX_MNT_ID).then_some(self.stat.stx_mnt_id)
+    }
+}
+
+impl Debug for Metadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metadata")
+            .field("file_type", &self.file_type())
+            .field("len", &self.len())
+            .field("mode", &format_args!("{:#o}", self.mode()))
+            .field("ino", &self.ino())
+            .field("dev", &self.dev())
+            .field("modified", &self.modified().ok())
+            .field("created", &self.created().ok())
+            .field("mount_id", &self.mount_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A future for querying file metadata.
+///
+/// This future is returned by [metadata](super::metadata),
+/// [symlink_metadata](super::symlink_metadata) and
+/// [File::metadata](super::File::metadata). It yields the [Metadata] once the
+/// `statx(2)` call has completed.
+pub struct Statx<'a> {
+    io: ReactorIo,
+    dirfd: RawFd,
+    path: CString,
+    flags: i32,
+    err: Option<Error>,
+    stat: libc::statx,
+    _dirfd: PhantomData<BorrowedFd<'a>>,
+    _phantom: PhantomPinned,
+}
+
+impl Statx<'_> {
+    pub(super) fn new(dirfd: RawFd, path: Result<CString>, flags: i32) -> Self {
+        let (path, err) = match path {
+            Ok(path) => (path, None),
+            Err(e) => (CString::default(), Some(e)),
+        };
+
+        Self {
+            io: Reactor::new_io(),
+            dirfd,
+            path,
+            flags: flags | libc::AT_STATX_SYNC_AS_STAT,
+            err,
+            stat: unsafe { std::mem::zeroed() },
+            _dirfd: PhantomData,
+            _phantom: PhantomPinned,
+        }
+    }
+}
+
+impl Future for Statx<'_> {
+    type Output = Result<Metadata>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::Statx::new(
+                        types::Fd(this.dirfd),
+                        this.path.as_ptr(),
+                        &mut this.stat as *mut libc::statx as *mut types::statx,
+                    )
+                    .flags(this.flags)
+                    .mask(STATX_MASK)
+                    .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|_| Metadata { stat: this.stat }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::MetadataExt, time::SystemTime};
+
+    use assert_fs::{
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use crate::{
+        futures::fs::{metadata, symlink_metadata, File},
+        task::Executor,
+    };
+
+    #[test]
+    fn file_metadata() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("Hello, world!").unwrap();
+        let path = child.to_path_buf();
+        let std_meta = std::fs::metadata(&path).unwrap();
+
+        let meta = Executor::block_on(async move { metadata(&path).await.unwrap() });
+
+        assert!(meta.is_file());
+        assert!(!meta.is_dir());
+        assert_eq!(meta.len(), 13);
+        assert_eq!(meta.ino(), std_meta.ino());
+        assert_eq!(meta.dev(), std_meta.dev());
+        assert_eq!(meta.mode(), std_meta.mode());
+        assert_eq!(meta.modified().unwrap(), std_meta.modified().unwrap());
+        assert!(meta.modified().unwrap() <= SystemTime::now());
+    }
+
+    #[test]
+    fn dir_metadata() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.to_path_buf();
+
+        let meta = Executor::block_on(async move { metadata(&path).await.unwrap() });
+
+        assert!(meta.is_dir());
+        assert!(meta.file_type().is_dir());
+    }
+
+    #[test]
+    fn symlink_not_followed() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.child("target.txt");
+        target.write_str("data").unwrap();
+        let link = dir.child("link");
+        std::os::unix::fs::symlink(target.path(), link.path()).unwrap();
+        let link = link.to_path_buf();
+
+        Executor::block_on(async move {
+            assert!(metadata(&link).await.unwrap().is_file());
+            assert!(symlink_metadata(&link).await.unwrap().is_symlink());
+        });
+    }
+
+    #[test]
+    fn open_file_metadata() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("abc").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = File::open(&path).await.unwrap();
+            let meta = f.metadata().await.unwrap();
+
+            assert!(meta.is_file());
+            assert_eq!(meta.len(), 3);
+        });
+    }
+
+    #[test]
+    fn missing_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("missing").to_path_buf();
+
+        Executor::block_on(async move {
+            let err = metadata(&path).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        });
+    }
+}
@@ -0,0 +1,749 @@
+//! Async filesystem operations.
+//!
+//! This module implements an async version of [std::fs]. It allows files to be
+//! opened, read from and written to asynchronously. Use [OpenOptions] for
+//! control over how a file is opened, such as its access mode, whether it is
+//! truncated or created and the permissions it is created with. File
+//! metadata can be queried with [metadata], [symlink_metadata] and
+//! [File::metadata], and directory entries can be removed, renamed and
+//! linked with [remove_file], [remove_dir], [rename], [hard_link] and
+//! [symlink]. Whole trees can be created with [create_dir_all] or a
+//! [DirBuilder], listed with [read_dir] and removed with [remove_dir_all]. A
+//! [Dir] handle performs operations relative to an open directory. Open files
+//! can be resized with [File::set_len] and have space allocated or released
+//! with [File::allocate]. Whole files can be read with [read] and
+//! [read_to_string], written with [write] and copied with [copy].
+//!
+//! # Example
+//!
+//! Here is an example of creating a new file and writing the string "Hello,
+//! world!", seeking to the start and reading it back into a buffer.
+//!
+//! ```
+//! use trale::task::Executor;
+//! use trale::futures::fs::File;
+//! use trale::futures::write::AsyncWrite;
+//! use crate::trale::futures::read::AsyncRead;
+//! use std::io::Seek;
+//!# use assert_fs::TempDir;
+//!# use assert_fs::fixture::PathChild;
+//!
+//! Executor::block_on(async {
+//!#     let dir = TempDir::new().unwrap();
+//!#     let path = dir.child("test.txt").to_path_buf();
+//!     let mut buf = [0; 1024];
+//!     let mut file = File::create(path).await?;
+//!     file.write("Hello, world!".as_bytes()).await?;
+//!
+//!     file.rewind()?;
+//!
+//!     let len = file.read(&mut buf).await?;
+//!     assert_eq!(&buf[..len], "Hello, world!".as_bytes());
+//!#     Ok::<(), std::io::Error>(())
+//! });
+//! ```
+use std::{
+    ffi::CString,
+    future::Future,
+    io::{self, Result, Seek},
+    marker::{PhantomData, PhantomPinned},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    path::Path,
+};
+
+use io_uring::{opcode, types};
+
+use crate::reactor::{Reactor, ReactorIo};
+
+pub use contents::{read, read_to_string, write};
+pub use copy::copy;
+pub use dir::{Dir, DirOpen};
+pub use dir_builder::DirBuilder;
+pub use metadata::{FileType, Metadata, Statx};
+pub use open_options::OpenOptions;
+pub use ops::{Link, Rename, Symlink, Unlink};
+pub use read_dir::{DirEntry, ReadDir};
+pub use remove_dir_all::remove_dir_all;
+pub use resize::{Allocate, SetLen};
+pub use sync::{FileSync, SyncMode, WriteSynced};
+
+mod contents;
+mod copy;
+mod dir;
+mod dir_builder;
+mod metadata;
+mod offload;
+mod open_options;
+mod ops;
+mod read_dir;
+mod remove_dir_all;
+mod resize;
+mod sync;
+
+use super::{
+    read::{AsyncRead, AsyncReader},
+    write::{AsyncWrite, AsyncWriter},
+};
+
+/// An open file.
+///
+/// This object represents a file that has been opened by one of the
+/// [File::create] or [File::open] methods. It can be used for reading and
+/// writing data via the [File::read] and [File::write] functions respectively.
+pub struct File {
+    inner: OwnedFd,
+}
+
+/// A future for creating a directory.
+///
+/// This future can be `.await`ed in order to create a directory. If the
+/// directory could not be created an `Err` value is returned with the
+/// underlying error indicating the reason for failure.
+pub struct Mkdir<'a> {
+    io: ReactorIo,
+    dirfd: RawFd,
+    path: CString,
+    mode: u32,
+    err: Option<io::Error>,
+    _dirfd: PhantomData<BorrowedFd<'a>>,
+}
+
+impl Mkdir<'_> {
+    fn new(dirfd: RawFd, path: Result<CString>, mode: u32) -> Self {
+        let (path, err) = match path {
+            Ok(path) => (path, None),
+            Err(e) => (CString::default(), Some(e)),
+        };
+
+        Self {
+            io: Reactor::new_io(),
+            dirfd,
+            path,
+            mode,
+            err,
+            _dirfd: PhantomData,
+        }
+    }
+}
+
+/// A future for opening a file.
+///
+/// This future can be `.await`ed in order to obtain an open [File] object. It
+/// is created by [OpenOptions::open] or one of the [File::open] or
+/// [File::create] methods. Note that if the file could not be opened and/or
+/// created an `Err` value is returned with the underlying error indicating the
+/// reason for failure.
+pub struct FileOpen<'a> {
+    io: ReactorIo,
+    dirfd: RawFd,
+    path: CString,
+    flags: i32,
+    mode: u32,
+    resolve: u64,
+    how: types::OpenHow,
+    err: Option<io::Error>,
+    _dirfd: PhantomData<BorrowedFd<'a>>,
+    _phantom: PhantomPinned,
+}
+
+impl FileOpen<'_> {
+    /// Create a future which opens `path` relative to `dirfd`. A non-zero
+    /// `resolve` restricts path resolution with `openat2(2)`.
+    fn new(
+        dirfd: RawFd,
+        path: Result<CString>,
+        flags: Result<i32>,
+        mode: u32,
+        resolve: u64,
+    ) -> Self {
+        let (path, flags, err) = match (path, flags) {
+            (Ok(path), Ok(flags)) => (path, flags, None),
+            (Err(e), _) | (_, Err(e)) => (CString::default(), 0, Some(e)),
+        };
+
+        Self {
+            io: Reactor::new_io(),
+            dirfd,
+            path,
+            flags,
+            mode,
+            resolve,
+            how: types::OpenHow::new(),
+            err,
+            _dirfd: PhantomData,
+            _phantom: PhantomPinned,
+        }
+    }
+}
+
+impl Future for FileOpen<'_> {
+    type Output = Result<File>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return std::task::Poll::Ready(Err(err));
+        }
+
+        this.io
+            .submit_or_get_result(|| {
+                let entry = if this.resolve == 0 {
+                    opcode::OpenAt::new(types::Fd(this.dirfd), this.path.as_ptr())
+                        .flags(this.flags)
+                        .mode(this.mode)
+                        .build()
+                } else {
+                    // Unlike openat(2), openat2(2) rejects a mode unless a
+                    // file may be created.
+                    let creates = this.flags & libc::O_CREAT != 0
+                        || this.flags & libc::O_TMPFILE == libc::O_TMPFILE;
+                    let mode = if creates { this.mode } else { 0 };
+
+                    this.how = types::OpenHow::new()
+                        .flags(this.flags as u64)
+                        .mode(mode as u64)
+                        .resolve(this.resolve);
+
+                    opcode::OpenAt2::new(types::Fd(this.dirfd), this.path.as_ptr(), &this.how)
+                        .build()
+                };
+
+                (entry, cx.waker().clone())
+            })
+            .map(|x| {
+                x.map(|x| File {
+                    inner: unsafe { OwnedFd::from_raw_fd(x) },
+                })
+            })
+    }
+}
+
+impl Future for Mkdir<'_> {
+    type Output = Result<()>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return std::task::Poll::Ready(Err(err));
+        }
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::MkDirAt::new(types::Fd(this.dirfd), this.path.as_ptr())
+                        .mode(this.mode)
+                        .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|_| ()))
+    }
+}
+impl File {
+    /// Attempt to open an existing file.
+    ///
+    /// This function takes a path to an already existant path and returns a
+    /// future which attempts to open it in read-only mode. If the path does
+    /// not exist `.await`ing the returned [FileOpen] future will yield an
+    /// error. Use [OpenOptions] to open a file for writing.
+    pub fn open(path: impl AsRef<Path>) -> FileOpen<'static> {
+        OpenOptions::new().read(true).open(path)
+    }
+
+    /// Attempt to create a new file.
+    ///
+    /// This function takes a path and returns a future which attempts to create
+    /// it with mode `0o666` (before the umask is applied) if it does not exist.
+    /// If the path already exists, the file is opened without being truncated.
+    /// In both cases, the file is opened in read/write mode.
+    pub fn create(path: impl AsRef<Path>) -> FileOpen<'static> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)
+    }
+
+    /// Attempt to create a new directory.
+    ///
+    /// This function takes a path and returns a future which attempts to create
+    /// the specified directory. If the path is relative, the base path is the
+    /// CWD of the program. The directory is created with mode `0o777`
+    /// (before the umask is applied); use a [DirBuilder] to choose another
+    /// mode or to create missing parent directories.
+    pub fn mkdir(path: impl AsRef<Path>) -> Mkdir<'static> {
+        Mkdir::new(libc::AT_FDCWD, cstr(path.as_ref()), 0o777)
+    }
+
+    /// Query the metadata of this file.
+    pub fn metadata(&self) -> Statx<'_> {
+        Statx::new(
+            self.inner.as_raw_fd(),
+            Ok(CString::default()),
+            libc::AT_EMPTY_PATH,
+        )
+    }
+
+    /// Read from the file at `offset` into `buf`, returning the number of
+    /// bytes read.
+    ///
+    /// Unlike [AsyncRead::read], this neither uses nor moves the file
+    /// position, and only needs a shared reference. Many reads, at different
+    /// offsets, can therefore be in flight on one file at the same time.
+    /// Offsets above `i64::MAX` fail with `EINVAL`.
+    pub async fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        check_offset(offset)?;
+
+        AsyncReader {
+            fd: self.inner.as_fd(),
+            buf,
+            io: Reactor::new_io(),
+            offset,
+        }
+        .await
+    }
+
+    /// Write `buf` to the file at `offset`, returning the number of bytes
+    /// written.
+    ///
+    /// Unlike [AsyncWrite::write], this neither uses nor moves the file
+    /// position, and only needs a shared reference. Note that if the file was
+    /// opened in append mode, Linux appends the data regardless of `offset`.
+    /// Offsets above `i64::MAX` fail with `EINVAL`.
+    pub async fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        check_offset(offset)?;
+
+        AsyncWriter {
+            fd: self.inner.as_fd(),
+            buf,
+            io: Reactor::new_io(),
+            offset,
+        }
+        .await
+    }
+
+    /// Flush the file's data and metadata to the storage device, like
+    /// `fsync(2)`.
+    pub fn sync_all(&self) -> FileSync<'_> {
+        FileSync::new(self.inner.as_fd(), SyncMode::All)
+    }
+
+    /// Flush the file's data to the storage device, like `fdatasync(2)`.
+    ///
+    /// Metadata that isn't needed to read the data back, such as the
+    /// modification time, may not be flushed.
+    pub fn sync_data(&self) -> FileSync<'_> {
+        FileSync::new(self.inner.as_fd(), SyncMode::Data)
+    }
+
+    /// Sync `len` bytes of the file starting at `offset`, like
+    /// `sync_file_range(2)`. A `len` of zero syncs to the end of the file.
+    ///
+    /// `flags` is a combination of `libc::SYNC_FILE_RANGE_WAIT_BEFORE`,
+    /// `libc::SYNC_FILE_RANGE_WRITE` and `libc::SYNC_FILE_RANGE_WAIT_AFTER`.
+    /// Note that this gives no durability guarantee for the file's metadata.
+    pub fn sync_range(&self, offset: u64, len: u32, flags: u32) -> FileSync<'_> {
+        FileSync::range(self.inner.as_fd(), offset, len, flags)
+    }
+
+    /// Write `buf` to the file at `offset` and then sync it, as with
+    /// [File::write_at] followed by [File::sync_all] or [File::sync_data]
+    /// depending on `mode`.
+    ///
+    /// Both operations are submitted to the kernel together, with the sync
+    /// linked to the write so that it runs as soon as the write completes.
+    /// The number of bytes written is returned once both have finished.
+    /// Offsets above `i64::MAX` fail with `EINVAL`.
+    pub fn write_at_synced<'a>(
+        &'a self,
+        buf: &'a [u8],
+        offset: u64,
+        mode: SyncMode,
+    ) -> WriteSynced<'a> {
+        WriteSynced::new(self.inner.as_fd(), buf, offset, mode)
+    }
+
+    /// Truncate or extend the file to `size` bytes, like `ftruncate(2)`.
+    ///
+    /// When extended, the new part of the file reads back as zeros. The file
+    /// must be open for writing.
+    pub fn set_len(&self, size: u64) -> SetLen<'_> {
+        SetLen::new(self.inner.as_fd(), size)
+    }
+
+    /// Manipulate the space allocated to the `len` bytes of the file starting
+    /// at `offset`, like `fallocate(2)`.
+    ///
+    /// A `mode` of zero allocates the range, extending the file if it ends
+    /// past the end of the file. Otherwise `mode` is a combination of the
+    /// `libc::FALLOC_FL_*` flags, most notably:
+    ///
+    /// - `libc::FALLOC_FL_KEEP_SIZE`: allocate without changing the file
+    ///   size.
+    /// - `libc::FALLOC_FL_PUNCH_HOLE`: deallocate the range, which then reads
+    ///   back as zeros. It must be combined with `libc::FALLOC_FL_KEEP_SIZE`.
+    /// - `libc::FALLOC_FL_ZERO_RANGE`: zero the range, preferably by
+    ///   converting it to unwritten extents rather than writing zeros.
+    ///
+    /// Not every filesystem supports every mode; unsupported modes fail with
+    /// `EOPNOTSUPP`.
+    pub fn allocate(&self, offset: u64, len: u64, mode: i32) -> Allocate<'_> {
+        Allocate::new(self.inner.as_fd(), offset, len, mode)
+    }
+}
+
+/// Query the metadata of the file at `path`, following symbolic links.
+pub fn metadata(path: impl AsRef<Path>) -> Statx<'static> {
+    Statx::new(libc::AT_FDCWD, cstr(path.as_ref()), 0)
+}
+
+/// Query the metadata of the file at `path` without following a symbolic link
+/// in the final component.
+pub fn symlink_metadata(path: impl AsRef<Path>) -> Statx<'static> {
+    Statx::new(
+        libc::AT_FDCWD,
+        cstr(path.as_ref()),
+        libc::AT_SYMLINK_NOFOLLOW,
+    )
+}
+
+/// Recursively create the directory at `path` along with any missing parent
+/// directories.
+///
+/// This is equivalent to `DirBuilder::new().recursive(true).create(path)`.
+pub fn create_dir_all(path: impl AsRef<Path>) -> impl Future<Output = Result<()>> + 'static {
+    DirBuilder::new().recursive(true).create(path)
+}
+
+/// Return a stream over the entries of the directory at `path`.
+///
+/// See [ReadDir] for details.
+pub fn read_dir(path: impl AsRef<Path>) -> ReadDir {
+    ReadDir::new(path.as_ref())
+}
+
+/// Remove the file at `path`.
+///
+/// This fails if `path` refers to a directory; use [remove_dir] instead.
+pub fn remove_file(path: impl AsRef<Path>) -> Unlink<'static> {
+    Unlink::new(libc::AT_FDCWD, cstr(path.as_ref()), 0)
+}
+
+/// Remove the empty directory at `path`.
+pub fn remove_dir(path: impl AsRef<Path>) -> Unlink<'static> {
+    Unlink::new(libc::AT_FDCWD, cstr(path.as_ref()), libc::AT_REMOVEDIR)
+}
+
+/// Rename `from` to `to`, replacing `to` if it already exists.
+pub fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Rename<'static> {
+    rename_with_flags(from, to, 0)
+}
+
+/// Rename `from` to `to` with `renameat2(2)` flags.
+///
+/// `flags` may be `libc::RENAME_NOREPLACE`, which fails with
+/// [AlreadyExists](io::ErrorKind::AlreadyExists) rather than replacing `to`,
+/// or `libc::RENAME_EXCHANGE`, which atomically swaps `from` and `to`.
+pub fn rename_with_flags(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    flags: u32,
+) -> Rename<'static> {
+    Rename::new(
+        libc::AT_FDCWD,
+        cstr(from.as_ref()),
+        libc::AT_FDCWD,
+        cstr(to.as_ref()),
+        flags,
+    )
+}
+
+/// Create a new hard link at `link` pointing to the file at `original`.
+pub fn hard_link(original: impl AsRef<Path>, link: impl AsRef<Path>) -> Link {
+    Link::new(cstr(original.as_ref()), cstr(link.as_ref()))
+}
+
+/// Create a symbolic link at `link` whose contents are `original`.
+///
+/// A relative `original` is resolved relative to the directory containing
+/// `link` when the link is followed.
+pub fn symlink(original: impl AsRef<Path>, link: impl AsRef<Path>) -> Symlink {
+    Symlink::new(cstr(original.as_ref()), cstr(link.as_ref()))
+}
+
+/// Convert `path` into a C string, failing if it contains an interior nul
+/// byte.
+fn cstr(path: &Path) -> Result<CString> {
+    CString::new(path.as_os_str().as_encoded_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a nul byte"))
+}
+
+/// Reject offsets which io_uring would take to mean the current file
+/// position, rather than passing them to the kernel.
+fn check_offset(offset: u64) -> Result<()> {
+    if offset > i64::MAX as u64 {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+
+    Ok(())
+}
+
+impl AsyncRead for File {
+    fn read(&mut self, buf: &mut [u8]) -> impl Future<Output = io::Result<usize>> {
+        AsyncReader {
+            fd: self.inner.as_fd(),
+            buf,
+            io: Reactor::new_io(),
+            offset: u64::MAX,
+        }
+    }
+}
+
+impl AsyncWrite for File {
+    fn write(&mut self, buf: &[u8]) -> impl Future<Output = io::Result<usize>> {
+        AsyncWriter {
+            fd: self.inner.as_fd(),
+            buf,
+            io: Reactor::new_io(),
+            offset: u64::MAX,
+        }
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: io::SeekFrom) -> Result<u64> {
+        let (off, whence) = match pos {
+            io::SeekFrom::Start(off) => (off as i64, libc::SEEK_SET),
+            io::SeekFrom::End(off) => (off, libc::SEEK_END),
+            io::SeekFrom::Current(off) => (off, libc::SEEK_CUR),
+        };
+
+        let res = unsafe { libc::lseek(self.inner.as_raw_fd(), off, whence) };
+
+        if res == -1 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(res as u64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Seek, rc::Rc};
+
+    use assert_fs::{
+        assert::PathAssert,
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use crate::{
+        futures::{read::AsyncRead, write::AsyncWrite},
+        task::Executor,
+    };
+
+    #[test]
+    fn simple_write() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut f = super::File::create(child_path).await.unwrap();
+            f.write("Hello, world!".as_bytes()).await.unwrap()
+        });
+
+        child.assert("Hello, world!");
+    }
+
+    #[test]
+    fn simple_read() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("Hello, async!").unwrap();
+
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut f = super::File::open(child_path).await.unwrap();
+            let mut buf = [0; 1024];
+            let len = f.read(&mut buf).await.unwrap();
+
+            assert_eq!(&buf[..len], "Hello, async!".as_bytes());
+        });
+    }
+
+    #[test]
+    fn no_file_open_error() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = super::File::open(child_path).await;
+
+            assert!(f.is_err());
+        });
+    }
+
+    #[test]
+    fn create_on_existing_file() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("data").unwrap();
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = super::File::create(child_path).await;
+
+            assert!(f.is_ok());
+        });
+
+        child.assert("data");
+    }
+
+    #[test]
+    fn consecutive_writes() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut f = super::File::create(child_path).await.unwrap();
+
+            f.write("Hello".as_bytes()).await.unwrap();
+
+            f.write("ABCD".as_bytes()).await.unwrap();
+        });
+
+        child.assert("HelloABCD");
+    }
+
+    #[test]
+    fn consecutive_reads() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("abcdef").unwrap();
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut buf = [0; 2];
+            let mut f = super::File::open(child_path).await.unwrap();
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "ab".as_bytes());
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "cd".as_bytes());
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "ef".as_bytes());
+        });
+    }
+
+    #[test]
+    fn simple_seek() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("abcdef").unwrap();
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut buf = [0; 2];
+            let mut f = super::File::open(child_path).await.unwrap();
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "ab".as_bytes());
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "cd".as_bytes());
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "ef".as_bytes());
+
+            f.seek(std::io::SeekFrom::Current(-4)).unwrap();
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "cd".as_bytes());
+
+            f.read(&mut buf).await.unwrap();
+            assert_eq!(buf, "ef".as_bytes());
+        });
+    }
+
+    #[test]
+    fn positional_io() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("0123456789").unwrap();
+        let child_path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = super::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(child_path)
+                .await
+                .unwrap();
+
+            f.write_at(b"ab", 4).await.unwrap();
+
+            let f = Rc::new(f);
+            let tasks: Vec<_> = [0, 4, 8]
+                .into_iter()
+                .map(|offset| {
+                    let f = f.clone();
+                    Executor::spawn(async move {
+                        let mut buf = [0; 4];
+                        let len = f.read_at(&mut buf, offset).await.unwrap();
+                        buf[..len].to_vec()
+                    })
+                })
+                .collect();
+
+            let mut chunks = Vec::new();
+            for task in tasks {
+                chunks.push(task.await);
+            }
+            assert_eq!(chunks, [&b"0123"[..], b"ab67", b"89"]);
+
+            // The file position is untouched.
+            let mut f = Rc::into_inner(f).unwrap();
+            let mut buf = [0; 6];
+            let len = f.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], b"0123ab");
+
+            // Offsets that don't fit in an off_t aren't the file position.
+            for offset in [i64::MAX as u64 + 1, u64::MAX] {
+                let err = f.read_at(&mut buf, offset).await.unwrap_err();
+                assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+                let err = f.write_at(b"x", offset).await.unwrap_err();
+                assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+            }
+
+            let len = f.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], b"6789");
+        });
+    }
+
+    #[test]
+    fn simple_mkdir() {
+        let dir = TempDir::new().unwrap();
+        let dir_path = dir.to_path_buf();
+
+        Executor::block_on(async move {
+            super::File::mkdir(dir_path.join("test_dir")).await.unwrap();
+        });
+
+        assert!(dir.child("test_dir").is_dir());
+    }
+}
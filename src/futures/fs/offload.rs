@@ -0,0 +1,127 @@
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Condvar, Mutex},
+    thread,
+};
+
+use crate::futures::channel::oneshot;
+
+/// The most helper threads that are ever started.
+const MAX_THREADS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct PoolState {
+    jobs: VecDeque<Job>,
+    idle: usize,
+    threads: usize,
+}
+
+/// A small pool of helper threads shared by the whole process.
+struct Pool {
+    state: Mutex<PoolState>,
+    cond: Condvar,
+}
+
+static POOL: Pool = Pool {
+    state: Mutex::new(PoolState {
+        jobs: VecDeque::new(),
+        idle: 0,
+        threads: 0,
+    }),
+    cond: Condvar::new(),
+};
+
+impl Pool {
+    fn submit(&'static self, job: Job) {
+        let mut state = self.state.lock().unwrap();
+
+        state.jobs.push_back(job);
+
+        // Only start another thread if the job would otherwise have to wait
+        // for one to become free.
+        if state.jobs.len() > state.idle && state.threads < MAX_THREADS {
+            state.threads += 1;
+
+            thread::Builder::new()
+                .name("trale-fs".into())
+                .spawn(move || self.work())
+                .expect("Should be able to spawn an fs helper thread");
+        } else {
+            self.cond.notify_one();
+        }
+    }
+
+    fn work(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            match state.jobs.pop_front() {
+                Some(job) => {
+                    drop(state);
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    state = self.state.lock().unwrap();
+                }
+                None => {
+                    state.idle += 1;
+                    state = self.cond.wait(state).unwrap();
+                    state.idle -= 1;
+                }
+            }
+        }
+    }
+}
+
+/// Run `f` on a helper thread.
+///
+/// This is for the few blocking system calls which io_uring has no opcode
+/// for, so that they don't stall the executor. The threads are shared by the
+/// whole process, and up to [MAX_THREADS] jobs run at the same time, so one
+/// slow call doesn't hold up the rest. The returned receiver fails if `f`
+/// panics.
+pub(super) fn offload<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> oneshot::Receiver<T> {
+    let (tx, rx) = oneshot::channel();
+
+    POOL.submit(Box::new(move || {
+        let _ = tx.send(f());
+    }));
+
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::offload;
+    use crate::task::Executor;
+
+    #[test]
+    fn runs_jobs() {
+        Executor::block_on(async {
+            let first = offload(|| std::thread::current().id());
+            let panicked = offload(|| panic!("job failed"));
+            let second = offload(|| std::thread::current().id());
+
+            assert_ne!(first.await.unwrap(), std::thread::current().id());
+            assert!(panicked.await.is_err());
+            second.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn slow_job_does_not_block_others() {
+        let (tx, rx) = mpsc::channel::<()>();
+
+        Executor::block_on(async move {
+            let slow = offload(move || rx.recv().unwrap());
+
+            offload(|| ()).await.unwrap();
+            tx.send(()).unwrap();
+            slow.await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    os::fd::RawFd,
+    path::Path,
+};
+
+use super::{cstr, FileOpen};
+
+/// Options and flags which can be used to configure how a file is opened.
+///
+/// This mirrors [std::fs::OpenOptions]: create a set of options with
+/// [OpenOptions::new], chain calls to the setters and finally call
+/// [OpenOptions::open] to obtain a [FileOpen] future. Invalid combinations of
+/// options, such as `truncate` without `write`, cause the future to resolve to
+/// an error of kind [ErrorKind::InvalidInput].
+///
+/// # Example
+///
+/// Open a file for appending, creating it with mode `0o600` if it does not
+/// already exist.
+///
+/// ```
+/// use trale::task::Executor;
+/// use trale::futures::fs::OpenOptions;
+/// use trale::futures::write::AsyncWrite;
+///# use assert_fs::TempDir;
+///# use assert_fs::fixture::PathChild;
+///
+/// Executor::block_on(async {
+///#     let dir = TempDir::new().unwrap();
+///#     let path = dir.child("log.txt").to_path_buf();
+///     let mut file = OpenOptions::new()
+///         .append(true)
+///         .create(true)
+///         .mode(0o600)
+///         .open(path)
+///         .await?;
+///
+///     file.write(b"started\n").await?;
+///#     Ok::<(), std::io::Error>(())
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    mode: u32,
+    custom_flags: i32,
+    resolve: u64,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenOptions {
+    /// Create a blank set of options with every option set to `false` and a
+    /// creation mode of `0o666`.
+    pub fn new() -> Self {
+        Self {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            mode: 0o666,
+            custom_flags: 0,
+            resolve: 0,
+        }
+    }
+
+    /// Set the option for read access.
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Set the option for write access.
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Set the option for append mode.
+    ///
+    /// All writes are made at the end of the file, regardless of the current
+    /// position. Setting this implies write access.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Set the option for truncating an existing file to a length of zero when
+    /// it is opened. Requires write access.
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Set the option to create the file if it does not exist. Requires write
+    /// or append access.
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Set the option to create a new file, failing if it already exists.
+    ///
+    /// The check and the creation are a single atomic operation (`O_EXCL`).
+    /// When set, [OpenOptions::create] and [OpenOptions::truncate] are
+    /// ignored.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Set the permission bits a newly created file is given, before the
+    /// process umask is applied. Defaults to `0o666`.
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Pass additional flags to `openat(2)`, such as `libc::O_DIRECT`,
+    /// `libc::O_CLOEXEC` or `libc::O_NOFOLLOW`.
+    ///
+    /// The access mode bits (`O_ACCMODE`) are masked out; use
+    /// [OpenOptions::read] and [OpenOptions::write] instead.
+    pub fn custom_flags(&mut self, flags: i32) -> &mut Self {
+        self.custom_flags = flags;
+        self
+    }
+
+    /// Restrict how the path is resolved with `openat2(2)` `RESOLVE_*` flags,
+    /// such as `libc::RESOLVE_BENEATH` or `libc::RESOLVE_NO_SYMLINKS`.
+    ///
+    /// This is mostly useful with [Dir::open_at](super::Dir::open_at), to
+    /// prevent a path from escaping the directory. Requires Linux 5.6 or later.
+    pub fn resolve(&mut self, flags: u64) -> &mut Self {
+        self.resolve = flags;
+        self
+    }
+
+    /// Return a future which opens the file at `path` with these options.
+    pub fn open(&self, path: impl AsRef<Path>) -> FileOpen<'static> {
+        self.open_at(libc::AT_FDCWD, path.as_ref())
+    }
+
+    /// Open `path` relative to `dirfd`.
+    pub(super) fn open_at<'a>(&self, dirfd: RawFd, path: &Path) -> FileOpen<'a> {
+        FileOpen::new(dirfd, cstr(path), self.flags(), self.mode, self.resolve)
+    }
+
+    fn flags(&self) -> Result<i32> {
+        Ok(self.access_mode()? | self.creation_mode()? | (self.custom_flags & !libc::O_ACCMODE))
+    }
+
+    fn access_mode(&self) -> Result<i32> {
+        match (self.read, self.write, self.append) {
+            (true, false, false) => Ok(libc::O_RDONLY),
+            (false, true, false) => Ok(libc::O_WRONLY),
+            (true, true, false) => Ok(libc::O_RDWR),
+            (false, _, true) => Ok(libc::O_WRONLY | libc::O_APPEND),
+            (true, _, true) => Ok(libc::O_RDWR | libc::O_APPEND),
+            (false, false, false) => Err(invalid("no access mode was requested")),
+        }
+    }
+
+    fn creation_mode(&self) -> Result<i32> {
+        match (self.write, self.append) {
+            (true, false) => {}
+            (false, false) => {
+                if self.truncate || self.create || self.create_new {
+                    return Err(invalid("creating or truncating requires write access"));
+                }
+            }
+            (_, true) => {
+                if self.truncate && !self.create_new {
+                    return Err(invalid("cannot truncate a file opened for appending"));
+                }
+            }
+        }
+
+        Ok(match (self.create, self.truncate, self.create_new) {
+            (false, false, false) => 0,
+            (true, false, false) => libc::O_CREAT,
+            (false, true, false) => libc::O_TRUNC,
+            (true, true, false) => libc::O_CREAT | libc::O_TRUNC,
+            (_, _, true) => libc::O_CREAT | libc::O_EXCL,
+        })
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, os::unix::fs::PermissionsExt};
+
+    use assert_fs::{
+        assert::PathAssert,
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use super::OpenOptions;
+    use crate::{
+        futures::{read::AsyncRead, write::AsyncWrite},
+        task::Executor,
+    };
+
+    #[test]
+    fn read_only_file() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("ro").unwrap();
+        std::fs::set_permissions(child.path(), PermissionsExt::from_mode(0o444)).unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut f = OpenOptions::new().read(true).open(&path).await.unwrap();
+            let mut buf = [0; 8];
+            let len = f.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], b"ro");
+
+            assert!(f.write(b"x").await.is_err());
+        });
+    }
+
+    #[test]
+    fn create_new_fails_on_existing() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("data").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let err = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+                .err()
+                .unwrap();
+
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        });
+    }
+
+    #[test]
+    fn truncate_and_append() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        child.write_str("old contents").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut f = OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .open(&path)
+                .await
+                .unwrap();
+            f.write(b"Hello").await.unwrap();
+            drop(f);
+
+            let mut f = OpenOptions::new().append(true).open(&path).await.unwrap();
+            f.write(b", world!").await.unwrap();
+        });
+
+        child.assert("Hello, world!");
+    }
+
+    #[test]
+    fn create_with_mode() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .mode(0o600)
+                .custom_flags(libc::O_CLOEXEC)
+                .open(&path)
+                .await
+                .unwrap();
+        });
+
+        let mode = std::fs::metadata(child.path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn invalid_combinations() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("test.txt").to_path_buf();
+
+        Executor::block_on(async move {
+            for opts in [
+                OpenOptions::new(),
+                OpenOptions::new().read(true).create(true).clone(),
+                OpenOptions::new().append(true).truncate(true).clone(),
+            ] {
+                let err = opts.open(&path).await.err().unwrap();
+                assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            }
+        });
+    }
+
+    #[test]
+    fn nul_in_path() {
+        Executor::block_on(async {
+            let err = OpenOptions::new()
+                .read(true)
+                .open("bad\0path")
+                .await
+                .err()
+                .unwrap();
+
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        });
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    ffi::CString,
+    future::Future,
+    io::{Error, Result},
+    marker::PhantomData,
+    os::fd::{BorrowedFd, RawFd},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use io_uring::{opcode, types};
+
+use crate::reactor::{Reactor, ReactorIo};
+
+/// Split a path conversion result into the path and a deferred error, which
+/// is returned when the future is first polled.
+fn defer<T: Default>(res: Result<T>) -> (T, Option<Error>) {
+    match res {
+        Ok(v) => (v, None),
+        Err(e) => (T::default(), Some(e)),
+    }
+}
+
+/// A future for removing a file or an empty directory.
+///
+/// This future is returned by [remove_file](super::remove_file) and
+/// [remove_dir](super::remove_dir). If the entry could not be removed an `Err`
+/// value is returned with the underlying error indicating the reason for
+/// failure.
+pub struct Unlink<'a> {
+    io: ReactorIo,
+    dirfd: RawFd,
+    path: CString,
+    flags: i32,
+    err: Option<Error>,
+    _dirfd: PhantomData<BorrowedFd<'a>>,
+}
+
+impl Unlink<'_> {
+    pub(super) fn new(dirfd: RawFd, path: Result<CString>, flags: i32) -> Self {
+        let (path, err) = defer(path);
+
+        Self {
+            io: Reactor::new_io(),
+            dirfd,
+            path,
+            flags,
+            err,
+            _dirfd: PhantomData,
+        }
+    }
+}
+
+impl Future for Unlink<'_> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::UnlinkAt::new(types::Fd(this.dirfd), this.path.as_ptr())
+                        .flags(this.flags)
+                        .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|_| ()))
+    }
+}
+
+/// A future for renaming a file or directory.
+///
+/// This future is returned by [rename](super::rename) and
+/// [rename_with_flags](super::rename_with_flags). If the entry could not be
+/// renamed an `Err` value is returned with the underlying error indicating the
+/// reason for failure.
+pub struct Rename<'a> {
+    io: ReactorIo,
+    olddirfd: RawFd,
+    oldpath: CString,
+    newdirfd: RawFd,
+    newpath: CString,
+    flags: u32,
+    err: Option<Error>,
+    _dirfd: PhantomData<BorrowedFd<'a>>,
+}
+
+impl Rename<'_> {
+    pub(super) fn new(
+        olddirfd: RawFd,
+        oldpath: Result<CString>,
+        newdirfd: RawFd,
+        newpath: Result<CString>,
+        flags: u32,
+    ) -> Self {
+        let ((oldpath, newpath), err) = defer(oldpath.and_then(|old| Ok((old, newpath?))));
+
+        Self {
+            io: Reactor::new_io(),
+            olddirfd,
+            oldpath,
+            newdirfd,
+            newpath,
+            flags,
+            err,
+            _dirfd: PhantomData,
+        }
+    }
+}
+
+impl Future for Rename<'_> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::RenameAt::new(
+                        types::Fd(this.olddirfd),
+                        this.oldpath.as_ptr(),
+                        types::Fd(this.newdirfd),
+                        this.newpath.as_ptr(),
+                    )
+                    .flags(this.flags)
+                    .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|_| ()))
+    }
+}
+
+/// A future for creating a hard link.
+///
+/// This future is returned by [hard_link](super::hard_link). If the link could
+/// not be created an `Err` value is returned with the underlying error
+/// indicating the reason for failure.
+pub struct Link {
+    io: ReactorIo,
+    oldpath: CString,
+    newpath: CString,
+    err: Option<Error>,
+}
+
+impl Link {
+    pub(super) fn new(oldpath: Result<CString>, newpath: Result<CString>) -> Self {
+        let ((oldpath, newpath), err) = defer(oldpath.and_then(|old| Ok((old, newpath?))));
+
+        Self {
+            io: Reactor::new_io(),
+            oldpath,
+            newpath,
+            err,
+        }
+    }
+}
+
+impl Future for Link {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::LinkAt::new(
+                        types::Fd(libc::AT_FDCWD),
+                        this.oldpath.as_ptr(),
+                        types::Fd(libc::AT_FDCWD),
+                        this.newpath.as_ptr(),
+                    )
+                    .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|_| ()))
+    }
+}
+
+/// A future for creating a symbolic link.
+///
+/// This future is returned by [symlink](super::symlink). If the link could not
+/// be created an `Err` value is returned with the underlying error indicating
+/// the reason for failure.
+pub struct Symlink {
+    io: ReactorIo,
+    target: CString,
+    linkpath: CString,
+    err: Option<Error>,
+}
+
+impl Symlink {
+    pub(super) fn new(target: Result<CString>, linkpath: Result<CString>) -> Self {
+        let ((target, linkpath), err) = defer(target.and_then(|t| Ok((t, linkpath?))));
+
+        Self {
+            io: Reactor::new_io(),
+            target,
+            linkpath,
+            err,
+        }
+    }
+}
+
+impl Future for Symlink {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::SymlinkAt::new(
+                        types::Fd(libc::AT_FDCWD),
+                        this.target.as_ptr(),
+                        this.linkpath.as_ptr(),
+                    )
+                    .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|_| ()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use assert_fs::{
+        assert::PathAssert,
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use crate::{
+        futures::fs::{hard_link, remove_dir, remove_file, rename, rename_with_flags, symlink},
+        task::Executor,
+    };
+
+    #[test]
+    fn remove_file_and_dir() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.child("test.txt");
+        file.write_str("data").unwrap();
+        let sub = dir.child("sub");
+        std::fs::create_dir(sub.path()).unwrap();
+        let (file_path, sub_path) = (file.to_path_buf(), sub.to_path_buf());
+
+        Executor::block_on(async move {
+            assert!(remove_dir(&file_path).await.is_err());
+            assert!(remove_file(&sub_path).await.is_err());
+
+            remove_file(&file_path).await.unwrap();
+            remove_dir(&sub_path).await.unwrap();
+
+            let err = remove_file(&file_path).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+        });
+
+        assert!(!file.path().exists());
+        assert!(!sub.path().exists());
+    }
+
+    #[test]
+    fn simple_rename() {
+        let dir = TempDir::new().unwrap();
+        let from = dir.child("from.txt");
+        from.write_str("data").unwrap();
+        let to = dir.child("to.txt");
+        let (from_path, to_path) = (from.to_path_buf(), to.to_path_buf());
+
+        Executor::block_on(async move {
+            rename(&from_path, &to_path).await.unwrap();
+        });
+
+        assert!(!from.path().exists());
+        to.assert("data");
+    }
+
+    #[test]
+    fn rename_noreplace_and_exchange() {
+        let dir = TempDir::new().unwrap();
+        let a = dir.child("a.txt");
+        a.write_str("a").unwrap();
+        let b = dir.child("b.txt");
+        b.write_str("b").unwrap();
+        let (a_path, b_path) = (a.to_path_buf(), b.to_path_buf());
+
+        Executor::block_on(async move {
+            let err = rename_with_flags(&a_path, &b_path, libc::RENAME_NOREPLACE)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+            rename_with_flags(&a_path, &b_path, libc::RENAME_EXCHANGE)
+                .await
+                .unwrap();
+        });
+
+        a.assert("b");
+        b.assert("a");
+    }
+
+    #[test]
+    fn links() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.child("target.txt");
+        target.write_str("data").unwrap();
+        let hard = dir.child("hard");
+        let soft = dir.child("soft");
+        let (target_path, hard_path, soft_path) =
+            (target.to_path_buf(), hard.to_path_buf(), soft.to_path_buf());
+
+        Executor::block_on(async move {
+            hard_link(&target_path, &hard_path).await.unwrap();
+            symlink("target.txt", &soft_path).await.unwrap();
+
+            let err = symlink("target.txt", &soft_path).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        });
+
+        hard.assert("data");
+        soft.assert("data");
+        assert_eq!(
+            std::fs::read_link(soft.path()).unwrap(),
+            std::path::Path::new("target.txt")
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ffi::{CStr, CString, OsStr, OsString},
+    future::{poll_fn, Future},
+    io::{Error, Result},
+    os::{
+        fd::{AsRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use tokio_stream::Stream;
+
+use crate::futures::channel::oneshot;
+
+use super::{cstr, offload::offload, symlink_metadata, FileOpen, FileType, Statx};
+
+/// Size of the buffer handed to each `getdents64(2)` call.
+const BUF_SIZE: usize = 32 * 1024;
+
+/// A directory entry as returned by `getdents64(2)`.
+pub(super) struct RawEntry {
+    pub(super) name: CString,
+    pub(super) ino: u64,
+    pub(super) d_type: u8,
+}
+
+/// Read the next batch of entries from `dir`, skipping `.` and `..`. Returns
+/// `None` once the end of the directory has been reached.
+fn getdents(dir: &OwnedFd, buf: &mut [u8]) -> Result<Option<Vec<RawEntry>>> {
+    let len = unsafe {
+        libc::syscall(
+            libc::SYS_getdents64,
+            dir.as_raw_fd(),
+            buf.as_mut_ptr(),
+            buf.len(),
+        )
+    };
+
+    if len == -1 {
+        return Err(Error::last_os_error());
+    }
+
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::new();
+    let mut off = 0;
+
+    while off < len as usize {
+        // struct linux_dirent64 {
+        //     u64 d_ino; s64 d_off; u16 d_reclen; u8 d_type; char d_name[];
+        // }
+        let rec = &buf[off..];
+        let ino = u64::from_ne_bytes(rec[0..8].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(rec[16..18].try_into().unwrap()) as usize;
+        let d_type = rec[18];
+        let name = CStr::from_bytes_until_nul(&rec[19..reclen]).unwrap();
+
+        if name != c"." && name != c".." {
+            entries.push(RawEntry {
+                name: name.to_owned(),
+                ino,
+                d_type,
+            });
+        }
+
+        off += reclen;
+    }
+
+    Ok(Some(entries))
+}
+
+type Batch = Result<Option<Vec<RawEntry>>>;
+
+thread_local! {
+    static BUF: RefCell<Vec<u8>> = RefCell::new(vec![0; BUF_SIZE]);
+}
+
+/// Reads batches of entries from a directory on a helper thread.
+///
+/// io_uring has no opcode for `getdents64(2)`, so each batch is read with
+/// [offload] to avoid blocking the executor. Only one batch is requested at a
+/// time.
+struct BatchReader {
+    dir: Arc<OwnedFd>,
+    batch: Option<oneshot::Receiver<Batch>>,
+}
+
+impl BatchReader {
+    fn new(dir: Arc<OwnedFd>) -> Self {
+        Self { dir, batch: None }
+    }
+
+    fn poll_batch(&mut self, cx: &mut Context<'_>) -> Poll<Batch> {
+        let batch = self.batch.get_or_insert_with(|| {
+            let dir = self.dir.clone();
+            offload(move || BUF.with_borrow_mut(|buf| getdents(&dir, buf)))
+        });
+
+        let batch = ready!(Pin::new(batch).poll(cx));
+        self.batch = None;
+
+        Poll::Ready(batch.unwrap_or_else(|_| Err(Error::other("failed to read directory"))))
+    }
+}
+
+/// Read every entry of `dir`.
+pub(super) async fn read_all(dir: Arc<OwnedFd>) -> Result<Vec<RawEntry>> {
+    let mut reader = BatchReader::new(dir);
+    let mut entries = Vec::new();
+
+    while let Some(batch) = poll_fn(|cx| reader.poll_batch(cx)).await? {
+        entries.extend(batch);
+    }
+
+    Ok(entries)
+}
+
+/// An entry inside a directory, as yielded by [ReadDir].
+pub struct DirEntry {
+    dir: Arc<PathBuf>,
+    entry: RawEntry,
+}
+
+impl DirEntry {
+    /// Returns the full path to this entry, formed by joining the path passed
+    /// to [read_dir](super::read_dir) with the entry's file name.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(self.file_name())
+    }
+
+    /// Returns the name of this entry within its directory.
+    pub fn file_name(&self) -> OsString {
+        OsStr::from_bytes(self.entry.name.to_bytes()).to_os_string()
+    }
+
+    /// Returns the inode number of this entry.
+    pub fn ino(&self) -> u64 {
+        self.entry.ino
+    }
+
+    /// Returns the type of this entry.
+    ///
+    /// This is normally taken from the directory listing without any I/O.
+    /// Filesystems which don't report types in their listing require a
+    /// `statx(2)` call, and so this function is async. Symbolic links are not
+    /// followed.
+    pub async fn file_type(&self) -> Result<FileType> {
+        match FileType::from_d_type(self.entry.d_type) {
+            Some(ty) => Ok(ty),
+            None => Ok(self.metadata().await?.file_type()),
+        }
+    }
+
+    /// Query the metadata of this entry without following symbolic links.
+    pub fn metadata(&self) -> Statx<'static> {
+        symlink_metadata(self.path())
+    }
+}
+
+enum State {
+    Opening(Pin<Box<FileOpen<'static>>>),
+    Reading(BatchReader),
+    Done,
+}
+
+/// A stream over the entries of a directory.
+///
+/// This stream is returned by [read_dir](super::read_dir) and yields a
+/// [DirEntry] for every entry except `.` and `..`, in no particular order.
+/// Entries are read in batches, so most items are returned without waiting.
+pub struct ReadDir {
+    dir: Arc<PathBuf>,
+    state: State,
+    entries: VecDeque<RawEntry>,
+}
+
+impl ReadDir {
+    pub(super) fn new(path: &Path) -> Self {
+        let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC;
+
+        Self {
+            dir: Arc::new(path.to_path_buf()),
+            state: State::Opening(Box::pin(FileOpen::new(
+                libc::AT_FDCWD,
+                cstr(path),
+                Ok(flags),
+                0,
+                0,
+            ))),
+            entries: VecDeque::new(),
+        }
+    }
+}
+
+impl Stream for ReadDir {
+    type Item = Result<DirEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(entry) = self.entries.pop_front() {
+                return Poll::Ready(Some(Ok(DirEntry {
+                    dir: self.dir.clone(),
+                    entry,
+                })));
+            }
+
+            let res = match &mut self.state {
+                State::Opening(open) => match ready!(open.as_mut().poll(cx)) {
+                    Ok(dir) => {
+                        self.state = State::Reading(BatchReader::new(Arc::new(dir.inner)));
+                        continue;
+                    }
+                    Err(e) => Err(e),
+                },
+                State::Reading(reader) => ready!(reader.poll_batch(cx)),
+                State::Done => return Poll::Ready(None),
+            };
+
+            match res {
+                Ok(Some(batch)) => self.entries.extend(batch),
+                Ok(None) => {
+                    self.state = State::Done;
+                }
+                Err(e) => {
+                    self.state = State::Done;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, io::ErrorKind, os::unix::fs::MetadataExt};
+
+    use assert_fs::{
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+    use tokio_stream::StreamExt;
+
+    use crate::{futures::fs::read_dir, task::Executor};
+
+    #[test]
+    fn list_entries() {
+        let dir = TempDir::new().unwrap();
+        dir.child("file").write_str("data").unwrap();
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        std::os::unix::fs::symlink("file", dir.join("link")).unwrap();
+        let file_ino = std::fs::metadata(dir.join("file")).unwrap().ino();
+        let path = dir.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut entries = read_dir(&path);
+            let mut names = HashSet::new();
+
+            while let Some(entry) = entries.next().await {
+                let entry = entry.unwrap();
+                let ty = entry.file_type().await.unwrap();
+                let name = entry.file_name().into_string().unwrap();
+
+                match name.as_str() {
+                    "file" => {
+                        assert!(ty.is_file());
+                        assert_eq!(entry.ino(), file_ino);
+                        assert_eq!(entry.metadata().await.unwrap().len(), 4);
+                    }
+                    "sub" => assert!(ty.is_dir()),
+                    "link" => assert!(ty.is_symlink()),
+                    _ => panic!("unexpected entry {name}"),
+                }
+
+                assert_eq!(entry.path(), path.join(&name));
+                names.insert(name);
+            }
+
+            assert_eq!(names.len(), 3);
+        });
+    }
+
+    #[test]
+    fn many_entries() {
+        let dir = TempDir::new().unwrap();
+
+        for i in 0..2000 {
+            std::fs::write(dir.join(format!("file-with-a-longer-name-{i}")), "").unwrap();
+        }
+
+        let path = dir.to_path_buf();
+
+        let count = Executor::block_on(async move {
+            let mut entries = read_dir(&path);
+            let mut count = 0;
+
+            while let Some(entry) = entries.next().await {
+                entry.unwrap();
+                count += 1;
+            }
+
+            count
+        });
+
+        assert_eq!(count, 2000);
+    }
+
+    #[test]
+    fn empty_and_missing() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.to_path_buf();
+
+        Executor::block_on(async move {
+            assert!(read_dir(&path).next().await.is_none());
+
+            let err = read_dir(path.join("missing")).next().await.unwrap();
+            assert_eq!(err.err().unwrap().kind(), ErrorKind::NotFound);
+        });
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    ffi::CString,
+    future::{poll_fn, Future},
+    io::{Error, ErrorKind, Result},
+    os::fd::{AsRawFd, OwnedFd},
+    path::Path,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::Poll,
+};
+
+use crate::futures::semaphore::Semaphore;
+
+use super::{
+    cstr,
+    read_dir::{read_all, RawEntry},
+    FileOpen, Statx, Unlink,
+};
+
+type BoxFuture = Pin<Box<dyn Future<Output = Result<()>>>>;
+
+/// Flags used to open every directory in the tree. `O_NOFOLLOW` makes the open
+/// fail rather than follow a symlink that has replaced a directory since it
+/// was listed.
+const DIR_FLAGS: i32 = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+
+/// The maximum number of directories that are listed at the same time. Each
+/// listing is offloaded from the executor, so this bounds the work handed off
+/// no matter how wide the tree is.
+const MAX_LISTINGS: usize = 8;
+
+/// The maximum number of opens, unlinks and stats in flight at the same time.
+/// Every entry of a directory is removed concurrently, so without this a large
+/// directory would overflow the submission queue.
+const MAX_OPS: usize = 256;
+
+struct Limits {
+    listings: Semaphore,
+    ops: Semaphore,
+}
+
+impl Limits {
+    /// Run `op` once there is room for another operation in flight.
+    async fn op<T>(&self, op: impl Future<Output = Result<T>>) -> Result<T> {
+        let _permit = self.ops.acquire(1).await.map_err(Error::other)?;
+        op.await
+    }
+}
+
+/// Remove the directory at `path` along with all of its contents.
+///
+/// Entries are removed relative to an open handle on their parent directory
+/// and symbolic links are never followed, so the removal cannot escape the
+/// tree even if it is modified concurrently. The entries of each directory are
+/// removed concurrently, though at most a few directories are listed, and a
+/// few hundred entries removed, at any one time. If `path` is itself a symbolic link, only the link is removed.
+pub fn remove_dir_all(path: impl AsRef<Path>) -> impl Future<Output = Result<()>> + 'static {
+    let path = cstr(path.as_ref());
+
+    async move {
+        let path = path?;
+        let meta = Statx::new(libc::AT_FDCWD, Ok(path.clone()), libc::AT_SYMLINK_NOFOLLOW).await?;
+
+        if meta.is_symlink() {
+            return Unlink::new(libc::AT_FDCWD, Ok(path), 0).await;
+        }
+
+        let dir = FileOpen::new(libc::AT_FDCWD, Ok(path.clone()), Ok(DIR_FLAGS), 0, 0).await?;
+        let limits = Rc::new(Limits {
+            listings: Semaphore::new(MAX_LISTINGS),
+            ops: Semaphore::new(MAX_OPS),
+        });
+        remove_contents(Arc::new(dir.inner), limits).await?;
+
+        Unlink::new(libc::AT_FDCWD, Ok(path), libc::AT_REMOVEDIR).await
+    }
+}
+
+fn remove_contents(dir: Arc<OwnedFd>, limits: Rc<Limits>) -> BoxFuture {
+    Box::pin(async move {
+        // The permit is only held while listing, not while removing the
+        // entries, so that subdirectories can't starve their parents.
+        let entries = {
+            let _permit = limits.listings.acquire(1).await.map_err(Error::other)?;
+            read_all(dir.clone()).await?
+        };
+
+        join_all(
+            entries
+                .into_iter()
+                .map(|entry| {
+                    Box::pin(remove_entry(dir.clone(), entry, limits.clone())) as BoxFuture
+                })
+                .collect(),
+        )
+        .await
+    })
+}
+
+async fn remove_entry(parent: Arc<OwnedFd>, entry: RawEntry, limits: Rc<Limits>) -> Result<()> {
+    let fd = parent.as_raw_fd();
+    let name = || Ok::<CString, _>(entry.name.clone());
+
+    let is_dir = match entry.d_type {
+        libc::DT_DIR => true,
+        libc::DT_UNKNOWN => limits
+            .op(Statx::new(fd, name(), libc::AT_SYMLINK_NOFOLLOW))
+            .await?
+            .is_dir(),
+        _ => false,
+    };
+
+    let res = if is_dir {
+        // Permits are only held for single operations, never across the
+        // removal of a subdirectory's contents, which needs permits of its own.
+        let child = limits
+            .op(FileOpen::new(fd, name(), Ok(DIR_FLAGS), 0, 0))
+            .await?;
+        remove_contents(Arc::new(child.inner), limits.clone()).await?;
+
+        limits.op(Unlink::new(fd, name(), libc::AT_REMOVEDIR)).await
+    } else {
+        limits.op(Unlink::new(fd, name(), 0)).await
+    };
+
+    match res {
+        // Someone else removed it first.
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+/// Drive all of `futs` to completion, returning the first error encountered.
+async fn join_all(mut futs: Vec<BoxFuture>) -> Result<()> {
+    let mut res = Ok(());
+
+    poll_fn(|cx| {
+        futs.retain_mut(|fut| match fut.as_mut().poll(cx) {
+            Poll::Ready(r) => {
+                if res.is_ok() {
+                    res = r;
+                }
+                false
+            }
+            Poll::Pending => true,
+        });
+
+        if futs.is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{
+        assert::PathAssert,
+        prelude::{FileWriteStr, PathChild},
+        TempDir,
+    };
+
+    use crate::{futures::fs::remove_dir_all, task::Executor};
+
+    #[test]
+    fn remove_tree() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.join("root");
+
+        for d in ["a/b/c", "a/d", "e"] {
+            std::fs::create_dir_all(root.join(d)).unwrap();
+        }
+        for f in ["a/b/c/1", "a/b/2", "a/d/3", "4"] {
+            std::fs::write(root.join(f), f).unwrap();
+        }
+        for i in 0..100 {
+            std::fs::write(root.join("e").join(i.to_string()), "x").unwrap();
+        }
+
+        Executor::block_on({
+            let root = root.clone();
+            async move { remove_dir_all(&root).await.unwrap() }
+        });
+
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn remove_wide_tree() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.join("root");
+
+        // Many more directories than can be listed at once, and more entries
+        // in one directory than fit in the submission queue.
+        for i in 0..500 {
+            let sub = root.join(i.to_string()).join("sub");
+            std::fs::create_dir_all(&sub).unwrap();
+            std::fs::write(sub.join("f"), "x").unwrap();
+        }
+        for i in 0..3000 {
+            std::fs::write(root.join(format!("file-{i}")), "x").unwrap();
+        }
+
+        Executor::block_on({
+            let root = root.clone();
+            async move { remove_dir_all(&root).await.unwrap() }
+        });
+
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn symlinks_not_followed() {
+        let dir = TempDir::new().unwrap();
+        let outside = dir.child("outside");
+        std::fs::create_dir(outside.path()).unwrap();
+        let keep = outside.child("keep.txt");
+        keep.write_str("keep").unwrap();
+
+        let root = dir.join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.join("link")).unwrap();
+
+        let top_link = dir.join("top_link");
+        std::os::unix::fs::symlink(outside.path(), &top_link).unwrap();
+
+        Executor::block_on({
+            let (root, top_link) = (root.clone(), top_link.clone());
+            async move {
+                remove_dir_all(&root).await.unwrap();
+                remove_dir_all(&top_link).await.unwrap();
+            }
+        });
+
+        assert!(!root.exists());
+        assert!(top_link.symlink_metadata().is_err());
+        keep.assert("keep");
+    }
+
+    #[test]
+    fn not_a_directory() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.child("file");
+        file.write_str("data").unwrap();
+        let path = file.to_path_buf();
+
+        Executor::block_on(async move {
+            assert!(remove_dir_all(&path).await.is_err());
+        });
+
+        file.assert("data");
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    future::Future,
+    io::{Error, Result},
+    os::fd::{AsRawFd, BorrowedFd},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use io_uring::{opcode, types};
+
+use crate::{
+    futures::channel::oneshot,
+    reactor::{Reactor, ReactorIo},
+};
+
+use super::offload::offload;
+
+enum Truncate {
+    Uring(ReactorIo),
+    /// The call is handed to a helper thread on the first poll.
+    Thread(Option<oneshot::Receiver<Result<()>>>),
+}
+
+/// A future for truncating or extending a file.
+///
+/// This future is returned by [File::set_len](super::File::set_len).
+/// `IORING_OP_FTRUNCATE` is used on Linux 6.9 and later; on older kernels the
+/// `ftruncate(2)` call is made on a helper thread instead.
+pub struct SetLen<'a> {
+    fd: BorrowedFd<'a>,
+    len: u64,
+    truncate: Truncate,
+}
+
+impl<'a> SetLen<'a> {
+    pub(super) fn new(fd: BorrowedFd<'a>, len: u64) -> Self {
+        if Reactor::is_supported(opcode::Ftruncate::CODE) {
+            Self {
+                fd,
+                len,
+                truncate: Truncate::Uring(Reactor::new_io()),
+            }
+        } else {
+            Self::offloaded(fd, len)
+        }
+    }
+
+    fn offloaded(fd: BorrowedFd<'a>, len: u64) -> Self {
+        Self {
+            fd,
+            len,
+            truncate: Truncate::Thread(None),
+        }
+    }
+}
+
+impl Future for SetLen<'_> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        match &mut this.truncate {
+            Truncate::Uring(io) => io
+                .submit_or_get_result(|| {
+                    (
+                        opcode::Ftruncate::new(types::Fd(this.fd.as_raw_fd()), this.len).build(),
+                        cx.waker().clone(),
+                    )
+                })
+                .map(|x| x.map(|_| ())),
+            Truncate::Thread(rx) => {
+                let rx = match rx {
+                    Some(rx) => rx,
+                    None => {
+                        // The helper thread gets its own descriptor in case
+                        // this future is dropped before the call returns.
+                        let fd = match this.fd.try_clone_to_owned() {
+                            Ok(fd) => fd,
+                            Err(e) => return Poll::Ready(Err(e)),
+                        };
+                        let len = this.len;
+
+                        rx.insert(offload(move || {
+                            let ret =
+                                unsafe { libc::ftruncate(fd.as_raw_fd(), len as libc::off_t) };
+                            if ret == -1 {
+                                Err(Error::last_os_error())
+                            } else {
+                                Ok(())
+                            }
+                        }))
+                    }
+                };
+
+                let res = ready!(Pin::new(rx).poll(cx));
+                Poll::Ready(res.unwrap_or_else(|_| Err(Error::other("ftruncate thread panicked"))))
+            }
+        }
+    }
+}
+
+/// A future for manipulating the space allocated to a file.
+///
+/// This future is returned by [File::allocate](super::File::allocate).
+pub struct Allocate<'a> {
+    fd: BorrowedFd<'a>,
+    offset: u64,
+    len: u64,
+    mode: i32,
+    io: ReactorIo,
+}
+
+impl<'a> Allocate<'a> {
+    pub(super) fn new(fd: BorrowedFd<'a>, offset: u64, len: u64, mode: i32) -> Self {
+        Self {
+            fd,
+            offset,
+            len,
+            mode,
+            io: Reactor::new_io(),
+        }
+    }
+}
+
+impl Future for Allocate<'_> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        this.io
+            .submit_or_get_result(|| {
+                (
+                    opcode::Fallocate::new(types::Fd(this.fd.as_raw_fd()), this.len)
+                        .offset(this.offset)
+                        .mode(this.mode)
+                        .build(),
+                    cx.waker().clone(),
+                )
+            })
+            .map(|x| x.map(|_| ()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsFd;
+
+    use assert_fs::{prelude::PathChild, TempDir};
+
+    use super::SetLen;
+    use crate::{
+        futures::fs::{File, OpenOptions},
+        task::Executor,
+    };
+
+    #[test]
+    fn set_len() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        std::fs::write(child.path(), "Hello, world!").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = OpenOptions::new().write(true).open(&path).await.unwrap();
+
+            f.set_len(5).await.unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), b"Hello");
+
+            SetLen::offloaded(f.inner.as_fd(), 8).await.unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), b"Hello\0\0\0");
+        });
+    }
+
+    #[test]
+    fn set_len_read_only() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        std::fs::write(child.path(), "data").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = File::open(&path).await.unwrap();
+
+            assert!(f.set_len(0).await.is_err());
+            assert!(SetLen::offloaded(f.inner.as_fd(), 0).await.is_err());
+        });
+    }
+
+    #[test]
+    fn set_len_not_polled() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        std::fs::write(child.path(), "data").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = OpenOptions::new().write(true).open(&path).await.unwrap();
+
+            drop(SetLen::offloaded(f.inner.as_fd(), 0));
+            // Had the dropped future started its call, this would leave the
+            // file zeroed or empty.
+            SetLen::offloaded(f.inner.as_fd(), 4).await.unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), b"data");
+        });
+    }
+
+    #[test]
+    fn allocate() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        std::fs::write(child.path(), "Hello, world!").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = OpenOptions::new().write(true).open(&path).await.unwrap();
+
+            f.allocate(0, 4096, 0).await.unwrap();
+            assert_eq!(f.metadata().await.unwrap().len(), 4096);
+
+            f.allocate(0, 8192, libc::FALLOC_FL_KEEP_SIZE)
+                .await
+                .unwrap();
+            assert_eq!(f.metadata().await.unwrap().len(), 4096);
+
+            // Not every filesystem supports punching holes or zeroing ranges.
+            let punch = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+            if f.allocate(0, 5, punch).await.is_ok() {
+                assert_eq!(&std::fs::read(&path).unwrap()[..7], b"\0\0\0\0\0, ");
+            }
+
+            if f.allocate(7, 5, libc::FALLOC_FL_ZERO_RANGE).await.is_ok() {
+                assert_eq!(&std::fs::read(&path).unwrap()[5..13], b", \0\0\0\0\0!");
+            }
+        });
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    future::Future,
+    io::{Error, Result},
+    os::fd::{AsRawFd, BorrowedFd},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use io_uring::{opcode, squeue, types};
+
+use crate::reactor::{Reactor, ReactorIo};
+
+use super::check_offset;
+
+/// Which data is flushed to the storage device by a sync.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Flush the file's data and all of its metadata, like `fsync(2)`.
+    All,
+    /// Flush the file's data and only the metadata needed to read it back,
+    /// such as its size, like `fdatasync(2)`.
+    Data,
+}
+
+#[derive(Clone, Copy)]
+enum SyncKind {
+    Full(SyncMode),
+    Range { offset: u64, len: u32, flags: u32 },
+}
+
+fn sync_entry(fd: BorrowedFd<'_>, kind: SyncKind) -> squeue::Entry {
+    let fd = types::Fd(fd.as_raw_fd());
+
+    match kind {
+        SyncKind::Full(SyncMode::All) => opcode::Fsync::new(fd).build(),
+        SyncKind::Full(SyncMode::Data) => opcode::Fsync::new(fd)
+            .flags(types::FsyncFlags::DATASYNC)
+            .build(),
+        SyncKind::Range { offset, len, flags } => opcode::SyncFileRange::new(fd, len)
+            .offset(offset)
+            .flags(flags)
+            .build(),
+    }
+}
+
+/// A future for flushing a file to its storage device.
+///
+/// This future is returned by [File::sync_all](super::File::sync_all),
+/// [File::sync_data](super::File::sync_data) and
+/// [File::sync_range](super::File::sync_range).
+pub struct FileSync<'a> {
+    fd: BorrowedFd<'a>,
+    kind: SyncKind,
+    io: ReactorIo,
+}
+
+impl<'a> FileSync<'a> {
+    pub(super) fn new(fd: BorrowedFd<'a>, mode: SyncMode) -> Self {
+        Self::with_kind(fd, SyncKind::Full(mode))
+    }
+
+    pub(super) fn range(fd: BorrowedFd<'a>, offset: u64, len: u32, flags: u32) -> Self {
+        Self::with_kind(fd, SyncKind::Range { offset, len, flags })
+    }
+
+    fn with_kind(fd: BorrowedFd<'a>, kind: SyncKind) -> Self {
+        Self {
+            fd,
+            kind,
+            io: Reactor::new_io(),
+        }
+    }
+}
+
+impl Future for FileSync<'_> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        this.io
+            .submit_or_get_result(|| (sync_entry(this.fd, this.kind), cx.waker().clone()))
+            .map(|x| x.map(|_| ()))
+    }
+}
+
+/// A future for a positional write followed by a sync.
+///
+/// This future is returned by
+/// [File::write_at_synced](super::File::write_at_synced). The write and the
+/// sync are submitted together as an io_uring link, so the sync only starts
+/// once the write has completed and no extra round trip through the executor
+/// is needed between them.
+pub struct WriteSynced<'a> {
+    fd: BorrowedFd<'a>,
+    buf: &'a [u8],
+    offset: u64,
+    mode: SyncMode,
+    err: Option<Error>,
+    write_io: ReactorIo,
+    write_res: Option<Result<i32>>,
+    sync_io: ReactorIo,
+    sync_res: Option<Result<i32>>,
+}
+
+impl<'a> WriteSynced<'a> {
+    pub(super) fn new(fd: BorrowedFd<'a>, buf: &'a [u8], offset: u64, mode: SyncMode) -> Self {
+        Self {
+            fd,
+            buf,
+            offset,
+            mode,
+            err: check_offset(offset).err(),
+            write_io: Reactor::new_io(),
+            write_res: None,
+            sync_io: Reactor::new_io(),
+            sync_res: None,
+        }
+    }
+}
+
+impl Future for WriteSynced<'_> {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        loop {
+            // Both entries are pushed to the submission queue back to back on
+            // the first poll, which is what makes the link take effect.
+            if this.write_res.is_none() {
+                if let Poll::Ready(res) = this.write_io.submit_or_get_result(|| {
+                    (
+                        opcode::Write::new(
+                            types::Fd(this.fd.as_raw_fd()),
+                            this.buf.as_ptr(),
+                            this.buf.len() as _,
+                        )
+                        .offset(this.offset)
+                        .build()
+                        .flags(squeue::Flags::IO_LINK),
+                        cx.waker().clone(),
+                    )
+                }) {
+                    this.write_res = Some(res);
+                }
+            }
+
+            if this.sync_res.is_none() {
+                if let Poll::Ready(res) = this.sync_io.submit_or_get_result(|| {
+                    (
+                        sync_entry(this.fd, SyncKind::Full(this.mode)),
+                        cx.waker().clone(),
+                    )
+                }) {
+                    this.sync_res = Some(res);
+                }
+            }
+
+            let (Some(write), Some(sync)) = (&this.write_res, &this.sync_res) else {
+                return Poll::Pending;
+            };
+
+            return Poll::Ready(match (write, sync) {
+                (Err(_), _) => Err(this.write_res.take().unwrap().unwrap_err()),
+                // A short write severs the link, cancelling the sync. Sync the
+                // part that was written on its own.
+                (Ok(_), Err(e)) if e.raw_os_error() == Some(libc::ECANCELED) => {
+                    this.sync_res = None;
+                    this.sync_io = Reactor::new_io();
+                    continue;
+                }
+                (Ok(_), Err(_)) => Err(this.sync_res.take().unwrap().unwrap_err()),
+                (Ok(n), Ok(_)) => Ok(*n as usize),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{assert::PathAssert, prelude::PathChild, TempDir};
+
+    use super::SyncMode;
+    use crate::{
+        futures::{fs::File, write::AsyncWrite},
+        task::Executor,
+    };
+
+    #[test]
+    fn sync_file() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let mut f = File::create(&path).await.unwrap();
+
+            f.write(b"Hello").await.unwrap();
+            f.sync_all().await.unwrap();
+            f.sync_data().await.unwrap();
+            f.sync_range(0, 0, libc::SYNC_FILE_RANGE_WRITE)
+                .await
+                .unwrap();
+        });
+
+        child.assert("Hello");
+    }
+
+    #[test]
+    fn linked_write_and_sync() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("wal.log");
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = File::create(&path).await.unwrap();
+
+            assert_eq!(
+                f.write_at_synced(b"first ", 0, SyncMode::Data)
+                    .await
+                    .unwrap(),
+                6
+            );
+            assert_eq!(
+                f.write_at_synced(b"second", 6, SyncMode::All)
+                    .await
+                    .unwrap(),
+                6
+            );
+        });
+
+        child.assert("first second");
+    }
+
+    #[test]
+    fn linked_write_error() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        std::fs::write(child.path(), "data").unwrap();
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            // Opened read-only, so the write fails and the sync is cancelled.
+            let f = File::open(&path).await.unwrap();
+
+            let err = f.write_at_synced(b"x", 0, SyncMode::All).await.unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+        });
+    }
+
+    #[test]
+    fn linked_write_bad_offset() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("test.txt");
+        let path = child.to_path_buf();
+
+        Executor::block_on(async move {
+            let f = File::create(&path).await.unwrap();
+
+            // Not taken to mean the file position.
+            for offset in [i64::MAX as u64 + 1, u64::MAX] {
+                let err = f
+                    .write_at_synced(b"x", offset, SyncMode::All)
+                    .await
+                    .unwrap_err();
+                assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+            }
+        });
+
+        child.assert("");
+    }
+}
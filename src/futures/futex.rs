@@ -0,0 +1,60 @@
+//! Futex helpers.
+//!
+//! On Linux 6.7 and later io_uring can wait on a futex with
+//! `IORING_OP_FUTEX_WAIT`, allowing a task to sleep on a plain [AtomicU32]
+//! without a file descriptor. Waking is done with the `futex(2)` system call
+//! so that it can be performed from any thread, including ones without a
+//! reactor.
+use std::{
+    io::{Error, ErrorKind, Result},
+    sync::atomic::AtomicU32,
+};
+
+use io_uring::{opcode, squeue};
+
+use crate::reactor::Reactor;
+
+/// `futex2(2)` flags for a process-private, 32-bit futex.
+const FUTEX2_SIZE_U32: u32 = 0x02;
+const FUTEX2_PRIVATE: u32 = 128;
+
+/// Returns `true` if the running kernel supports `IORING_OP_FUTEX_WAIT`.
+pub(crate) fn is_supported() -> bool {
+    Reactor::is_supported(opcode::FutexWait::CODE)
+}
+
+/// Build an SQE which completes once `word` is woken, or immediately with
+/// `EAGAIN` if `word` does not hold `expected` when the wait is submitted.
+pub(crate) fn wait_entry(word: &AtomicU32, expected: u32) -> squeue::Entry {
+    opcode::FutexWait::new(
+        word.as_ptr(),
+        expected as u64,
+        libc::FUTEX_BITSET_MATCH_ANY as u32 as u64,
+        FUTEX2_SIZE_U32 | FUTEX2_PRIVATE,
+    )
+    .build()
+}
+
+/// Wake at most `n` tasks waiting on `word`, returning the number woken.
+pub(crate) fn wake(word: &AtomicU32, n: u32) -> Result<usize> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+            n.min(i32::MAX as u32) as libc::c_int,
+        )
+    };
+
+    if ret == -1 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(ret as usize)
+}
+
+/// Returns `true` if a futex wait finished because the value did not match,
+/// which callers should treat like a wakeup.
+pub(crate) fn is_value_mismatch(err: &Error) -> bool {
+    err.kind() == ErrorKind::WouldBlock
+}
@@ -14,10 +14,17 @@
 //!
 //! The following sub-modules are exposed by the `futures` module:
 //!
+//! - `barrier`: Implements an async barrier for coordinating phases across tasks.
+//! - `channel`: Provides async oneshot, mpsc, broadcast and watch channels for passing values between tasks.
+//! - `condvar`: Implements an async condition variable for use with `mutex`.
 //! - `event`: Provides futures for inter-task event signaling.
 //! - `fs`: Provides futures for interacting with the\ filesystem.
 //! - `mutex`: Implements futures for task synchronization using a mutex-like primitive.
+//! - `notify`: Provides an in-process task notification primitive with broadcast support.
+//! - `once_cell`: Implements a cell which is lazily initialized by an async function.
 //! - `read`: Implements futures for reading from non-blocking file descriptors.
+//! - `rwlock`: Implements an async reader-writer lock.
+//! - `semaphore`: Implements an async counting semaphore for limiting concurrency.
 //! - `tcp`: Provides futures for handling TCP socket operations.
 //! - `timer`: Implements futures for timer-based tasks using `timerfd`.
 //! - `udp`: Provides futures for handling UDP socket operations.
@@ -26,12 +33,21 @@
 //! Together, these futures form the core of the `trale` executor's
 //! functionality, enabling the reactor to monitor and interact with various
 //! asynchronous operations.
+pub mod barrier;
+pub mod channel;
+pub mod condvar;
 pub mod event;
 pub mod fs;
+mod futex;
 pub mod mutex;
+pub mod notify;
+pub mod once_cell;
 pub mod read;
+pub mod rwlock;
+pub mod semaphore;
 mod sock_addr;
 pub mod tcp;
 pub mod timer;
 pub mod udp;
+mod waiters;
 pub mod write;
@@ -58,17 +58,137 @@
 //! Without an async-aware mutex, if one task holds the lock, the other would
 //! block the thread, causing a deadlock in the main thread because the async
 //! runtime wouldn't be able to progress.
-use super::event::Event;
+//!
+//! #### Fairness
+//!
+//! Tasks waiting for the lock are queued in FIFO order. When the lock is
+//! released it is handed directly to the longest-waiting task, so a task that
+//! repeatedly locks and unlocks the mutex can't starve the others.
+//!
+//! #### Owned and Mapped Guards
+//!
+//! [LockGuard] borrows the mutex. When the mutex is held in an [Arc],
+//! [Mutex::lock_owned] returns an [OwnedLockGuard] instead, which can be moved
+//! into a spawned task. [LockGuard::map] narrows a guard down to a part of
+//! the protected value, yielding a [MappedLockGuard].
+use super::waiters::WaitList;
 use std::{
     cell::UnsafeCell,
+    fmt::Display,
+    future::Future,
+    marker::PhantomData,
     ops::{Deref, DerefMut},
+    pin::{pin, Pin},
+    sync::{self, Arc},
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
 };
 
+struct State {
+    locked: bool,
+    waiters: WaitList<()>,
+}
+
+/// The lock itself, independent of the type of the protected value.
+struct RawMutex {
+    state: sync::Mutex<State>,
+}
+
+impl RawMutex {
+    fn try_lock(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        if state.locked || !state.waiters.is_empty() {
+            return false;
+        }
+
+        state.locked = true;
+
+        true
+    }
+
+    fn acquire(&self) -> Acquire<'_> {
+        Acquire {
+            raw: self,
+            key: None,
+        }
+    }
+
+    fn unlock(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        // Hand the lock straight to the next waiter, if there is one.
+        if !state.waiters.notify_front() {
+            state.locked = false;
+        }
+    }
+}
+
+/// A future which resolves once the lock has been acquired.
+struct Acquire<'a> {
+    raw: &'a RawMutex,
+    key: Option<usize>,
+}
+
+impl Future for Acquire<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.raw.state.lock().unwrap();
+
+        match self.key {
+            None if !state.locked && state.waiters.is_empty() => {
+                state.locked = true;
+                Poll::Ready(())
+            }
+            None => {
+                let key = state.waiters.push((), cx.waker());
+                drop(state);
+                self.key = Some(key);
+                Poll::Pending
+            }
+            Some(key) if state.waiters.is_notified(key) => {
+                state.waiters.remove(key);
+                drop(state);
+                self.key = None;
+                Poll::Ready(())
+            }
+            Some(key) => {
+                state.waiters.register(key, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        let Some(key) = self.key else {
+            return;
+        };
+
+        let (_, notified) = self.raw.state.lock().unwrap().waiters.remove(key);
+
+        // The lock was handed to us but will never be used.
+        if notified {
+            self.raw.unlock();
+        }
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
 /// An async-aware mutex
 ///
 /// See the [module-level documentation](self) for more information.
 pub struct Mutex<T> {
-    evt: Event,
+    raw: RawMutex,
     obj: UnsafeCell<T>,
 }
 
@@ -90,17 +210,57 @@ pub struct LockGuard<'a, T> {
     mtx: &'a Mutex<T>,
 }
 
+// Sharing a guard shares `&T`, so unlike the mutex itself it is only `Sync`
+// when `T` is.
+unsafe impl<T: Send + Sync> Sync for LockGuard<'_, T> {}
+
+/// A lock held by a task on a mutex held in an [Arc].
+///
+/// This behaves like [LockGuard] but keeps the mutex alive itself, so it can
+/// be moved into a spawned task. It is returned by [Mutex::lock_owned].
+pub struct OwnedLockGuard<T> {
+    mtx: Arc<Mutex<T>>,
+}
+
+unsafe impl<T: Send + Sync> Sync for OwnedLockGuard<T> {}
+
+/// A lock guard which gives access to a part of the locked value.
+///
+/// It is created by [LockGuard::map] and releases the lock when dropped.
+pub struct MappedLockGuard<'a, U: ?Sized> {
+    raw: &'a RawMutex,
+    obj: *mut U,
+    _marker: PhantomData<&'a mut U>,
+}
+
+unsafe impl<U: ?Sized + Send> Send for MappedLockGuard<'_, U> {}
+unsafe impl<U: ?Sized + Sync> Sync for MappedLockGuard<'_, U> {}
+
+/// The error returned by [Mutex::try_lock] when the mutex is already locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TryLockError;
+
+impl Display for TryLockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "mutex is locked")
+    }
+}
+
+impl std::error::Error for TryLockError {}
+
 impl<T> Mutex<T> {
     /// Create a new mutex by taking an initial value of an object. If the mutex
     /// fails to be created, then the value is dropped and an error returned. If
     /// the mutex was created, then the mutex which wraps the object is
     /// returned.
     pub fn new(obj: T) -> std::io::Result<Self> {
-        let evt = Event::new()?;
-        evt.notify_one()?;
-
         Ok(Self {
-            evt,
+            raw: RawMutex {
+                state: sync::Mutex::new(State {
+                    locked: false,
+                    waiters: WaitList::new(),
+                }),
+            },
             obj: UnsafeCell::new(obj),
         })
     }
@@ -110,11 +270,80 @@ impl<T> Mutex<T> {
     /// the [LockGuard] is returned without yielding. If the mutex is locked,
     /// then the task is put to sleep and will be rescheduled by the run-time
     /// once the mutex has been unlocked by another task.
-    pub async fn lock(&self) -> LockGuard<T> {
-        let mut evt = self.evt.clone();
-        evt.wait().await.unwrap();
+    pub async fn lock(&self) -> LockGuard<'_, T> {
+        self.raw.acquire().await;
+        LockGuard { mtx: self }
+    }
+
+    /// Lock a mutex held in an [Arc], returning a guard which doesn't borrow
+    /// the mutex. See [Mutex::lock].
+    pub async fn lock_owned(self: Arc<Self>) -> OwnedLockGuard<T> {
+        self.raw.acquire().await;
+        OwnedLockGuard { mtx: self }
+    }
+
+    /// Attempt to lock the mutex without waiting.
+    ///
+    /// This fails if the mutex is locked, or if other tasks are waiting for
+    /// it.
+    pub fn try_lock(&self) -> Result<LockGuard<'_, T>, TryLockError> {
+        if self.raw.try_lock() {
+            Ok(LockGuard { mtx: self })
+        } else {
+            Err(TryLockError)
+        }
+    }
+
+    /// Lock the mutex from a synchronous context, blocking the current thread
+    /// until the lock has been acquired.
+    ///
+    /// *Note* This function must not be called from within an async task, as
+    /// blocking the executor's thread prevents the task holding the lock from
+    /// making progress.
+    pub fn blocking_lock(&self) -> LockGuard<'_, T> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut acquire = pin!(self.raw.acquire());
+
+        while acquire.as_mut().poll(&mut cx).is_pending() {
+            thread::park();
+        }
+
         LockGuard { mtx: self }
     }
+
+    /// Obtain a mutable reference to the protected value. No locking is
+    /// needed since the mutex is borrowed mutably.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.obj.get_mut()
+    }
+
+    /// Consume the mutex, returning the protected value.
+    pub fn into_inner(self) -> T {
+        self.obj.into_inner()
+    }
+}
+
+impl<'a, T> LockGuard<'a, T> {
+    /// Make a [MappedLockGuard] for a part of the locked value.
+    ///
+    /// This is an associated function, rather than a method, to avoid
+    /// conflicting with methods on `T`.
+    pub fn map<U: ?Sized>(this: Self, f: impl FnOnce(&mut T) -> &mut U) -> MappedLockGuard<'a, U> {
+        let mtx = this.mtx;
+        let obj = f(unsafe { &mut *mtx.obj.get() });
+        std::mem::forget(this);
+
+        MappedLockGuard {
+            raw: &mtx.raw,
+            obj,
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn mutex(this: &Self) -> &'a Mutex<T> {
+        this.mtx
+    }
 }
 
 impl<T> Deref for LockGuard<'_, T> {
@@ -133,16 +362,60 @@ impl<T> DerefMut for LockGuard<'_, T> {
 
 impl<T> Drop for LockGuard<'_, T> {
     fn drop(&mut self) {
-        self.mtx.evt.notify_one().unwrap()
+        self.mtx.raw.unlock();
+    }
+}
+
+impl<T> Deref for OwnedLockGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mtx.obj.get() }
+    }
+}
+
+impl<T> DerefMut for OwnedLockGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mtx.obj.get() }
+    }
+}
+
+impl<T> Drop for OwnedLockGuard<T> {
+    fn drop(&mut self) {
+        self.mtx.raw.unlock();
+    }
+}
+
+impl<U: ?Sized> Deref for MappedLockGuard<'_, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.obj }
+    }
+}
+
+impl<U: ?Sized> DerefMut for MappedLockGuard<'_, U> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.obj }
+    }
+}
+
+impl<U: ?Sized> Drop for MappedLockGuard<'_, U> {
+    fn drop(&mut self) {
+        self.raw.unlock();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Mutex;
+    use super::{LockGuard, Mutex, TryLockError};
     use crate::{futures::timer::Timer, task::Executor};
     use anyhow::Result;
-    use std::{sync::Arc, time::Duration};
+    use std::{
+        sync::{self, Arc},
+        thread,
+        time::Duration,
+    };
 
     #[test]
     fn simple() -> Result<()> {
@@ -215,4 +488,106 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn try_lock() -> Result<()> {
+        let mtx = Mutex::new(1)?;
+
+        let lock = mtx.try_lock().unwrap();
+        assert_eq!(mtx.try_lock().err(), Some(TryLockError));
+        drop(lock);
+
+        *mtx.try_lock().unwrap() += 1;
+        assert_eq!(mtx.into_inner(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fifo_handoff() -> Result<()> {
+        let mtx = Arc::new(Mutex::new(Vec::new())?);
+
+        Executor::block_on({
+            let mtx = mtx.clone();
+            async move {
+                let lock = mtx.lock().await;
+                let mut tasks = Vec::new();
+
+                for i in 0..4 {
+                    let mtx = mtx.clone();
+                    tasks.push(Executor::spawn(async move {
+                        mtx.lock().await.push(i);
+                    }));
+                    Timer::sleep(Duration::from_millis(5)).unwrap().await;
+                }
+
+                drop(lock);
+
+                // The lock has been handed to the first waiter, so it can't
+                // be taken by a newcomer.
+                assert!(mtx.try_lock().is_err());
+
+                for task in tasks {
+                    task.await;
+                }
+            }
+        });
+
+        assert_eq!(*mtx.try_lock().unwrap(), [0, 1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn owned_and_mapped_guards() -> Result<()> {
+        let mtx = Arc::new(Mutex::new((0, String::new()))?);
+
+        Executor::block_on({
+            let mtx = mtx.clone();
+            async move {
+                let mut guard = mtx.clone().lock_owned().await;
+
+                let task = Executor::spawn(async move {
+                    Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                    guard.0 += 1;
+                });
+
+                let mut name = LockGuard::map(mtx.lock().await, |v| &mut v.1);
+                name.push_str("trale");
+                drop(name);
+
+                task.await;
+            }
+        });
+
+        assert_eq!(*mtx.try_lock().unwrap(), (1, String::from("trale")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocking_lock() -> Result<()> {
+        let mtx = Arc::new(Mutex::new(0)?);
+        let (tx, rx) = sync::mpsc::channel();
+
+        let t1 = {
+            let mtx = mtx.clone();
+            thread::spawn(move || {
+                Executor::block_on(async move {
+                    let mut lock = mtx.lock().await;
+                    tx.send(()).unwrap();
+                    Timer::sleep(Duration::from_millis(20)).unwrap().await;
+                    *lock += 1;
+                })
+            })
+        };
+
+        rx.recv().unwrap();
+        *mtx.blocking_lock() += 1;
+        assert_eq!(*mtx.blocking_lock(), 2);
+
+        t1.join().unwrap();
+
+        Ok(())
+    }
 }
@@ -0,0 +1,311 @@
+//! ### Async Task Notification
+//!
+//! This module provides [Notify], a **cross-thread, non-blocking** way for one
+//! task to tell others that something has happened. Unlike
+//! [Event](super::event::Event), whose waiters sleep on a futex or eventfd in
+//! the kernel, waiting tasks are kept in an in-process list; notifying a task
+//! on the same thread simply moves it onto the run queue without any system
+//! calls.
+//!
+//! A [Notify] supports two styles of notification:
+//!
+//! - [Notify::notify_one] wakes a single waiting task. If no task is waiting,
+//!   a *permit* is stored instead and the next call to [Notify::notified]
+//!   completes immediately. At most one permit is stored.
+//! - [Notify::notify_waiters] wakes every task that is currently waiting, for
+//!   example to broadcast that some configuration has been reloaded. It does
+//!   not store a permit.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::notify::Notify;
+//! use trale::futures::timer::Timer;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let notify = Arc::new(Notify::new());
+//!
+//! let tasks: Vec<_> = (0..3)
+//!     .map(|_| {
+//!         let notify = notify.clone();
+//!         Executor::spawn(async move { notify.notified().await })
+//!     })
+//!     .collect();
+//!
+//! Executor::block_on(async move {
+//!     Timer::sleep(Duration::from_millis(10)).unwrap().await;
+//!     notify.notify_waiters();
+//!
+//!     for task in tasks {
+//!         task.await;
+//!     }
+//! });
+//! ```
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use super::waiters::WaitList;
+
+struct State {
+    permit: bool,
+    /// Incremented by every call to [Notify::notify_waiters].
+    generation: u64,
+    /// Each waiter records whether it was woken by [Notify::notify_one], in
+    /// which case it owns the notification and must pass it on if dropped.
+    waiters: WaitList<bool>,
+}
+
+impl State {
+    fn notify_one(&mut self) {
+        match self.waiters.front_mut() {
+            Some(one) => {
+                *one = true;
+                self.waiters.notify_front();
+            }
+            None => self.permit = true,
+        }
+    }
+}
+
+/// Notifies one or more waiting tasks of an event.
+///
+/// See the [module-level documentation](self) for more information.
+pub struct Notify {
+    state: Mutex<State>,
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notify {
+    /// Create a new `Notify` without a stored permit.
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                permit: false,
+                generation: 0,
+                waiters: WaitList::new(),
+            }),
+        }
+    }
+
+    /// Wait for a notification.
+    ///
+    /// The returned future completes once it receives a permit from
+    /// [Notify::notify_one], or if [Notify::notify_waiters] is called at any
+    /// point after the future was created.
+    pub fn notified(&self) -> Notified<'_> {
+        let generation = self.state.lock().unwrap().generation;
+
+        Notified {
+            notify: self,
+            generation,
+            key: None,
+        }
+    }
+
+    /// Wake the longest-waiting task, or store a permit if there are no
+    /// waiters.
+    pub fn notify_one(&self) {
+        self.state.lock().unwrap().notify_one();
+    }
+
+    /// Wake all tasks that are currently waiting.
+    ///
+    /// No permit is stored, so tasks that start waiting after this call are
+    /// not woken.
+    pub fn notify_waiters(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        state.generation = state.generation.wrapping_add(1);
+
+        while state.waiters.notify_front() {}
+    }
+}
+
+/// A future returned by [Notify::notified].
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    generation: u64,
+    key: Option<usize>,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.notify.state.lock().unwrap();
+
+        match self.key {
+            None => {
+                if state.generation != self.generation {
+                    return Poll::Ready(());
+                }
+
+                if state.permit {
+                    state.permit = false;
+                    return Poll::Ready(());
+                }
+
+                let key = state.waiters.push(false, cx.waker());
+                drop(state);
+                self.key = Some(key);
+
+                Poll::Pending
+            }
+            Some(key) if state.waiters.is_notified(key) => {
+                state.waiters.remove(key);
+                drop(state);
+                self.key = None;
+
+                Poll::Ready(())
+            }
+            Some(key) => {
+                state.waiters.register(key, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let Some(key) = self.key else {
+            return;
+        };
+
+        let mut state = self.notify.state.lock().unwrap();
+
+        // Pass on a `notify_one` that this waiter will never observe.
+        if let (true, true) = state.waiters.remove(key) {
+            state.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Notify;
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Waker},
+        thread,
+        time::Duration,
+    };
+
+    fn is_ready(fut: std::pin::Pin<&mut impl Future>) -> bool {
+        fut.poll(&mut Context::from_waker(Waker::noop())).is_ready()
+    }
+
+    #[test]
+    fn permit_is_stored() {
+        let notify = Notify::new();
+
+        notify.notify_one();
+        notify.notify_one();
+
+        assert!(is_ready(pin!(notify.notified())));
+        assert!(!is_ready(pin!(notify.notified())));
+    }
+
+    #[test]
+    fn notify_waiters_wakes_all() {
+        let notify = Arc::new(Notify::new());
+        let woken = Arc::new(AtomicUsize::new(0));
+
+        Executor::block_on({
+            let notify = notify.clone();
+            let woken = woken.clone();
+            async move {
+                let tasks: Vec<_> = (0..3)
+                    .map(|_| {
+                        let notify = notify.clone();
+                        let woken = woken.clone();
+                        Executor::spawn(async move {
+                            notify.notified().await;
+                            woken.fetch_add(1, Ordering::Relaxed);
+                        })
+                    })
+                    .collect();
+
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                notify.notify_waiters();
+
+                for task in tasks {
+                    task.await;
+                }
+            }
+        });
+
+        assert_eq!(woken.load(Ordering::Relaxed), 3);
+
+        // No permit is left behind.
+        assert!(!is_ready(pin!(notify.notified())));
+    }
+
+    #[test]
+    fn notify_waiters_before_first_poll() {
+        let notify = Notify::new();
+        let fut = notify.notified();
+
+        notify.notify_waiters();
+
+        assert!(is_ready(pin!(fut)));
+    }
+
+    #[test]
+    fn dropped_waiter_forwards_notification() {
+        let notify = Notify::new();
+        let mut a = Box::pin(notify.notified());
+        let mut b = Box::pin(notify.notified());
+
+        assert!(!is_ready(a.as_mut()));
+        assert!(!is_ready(b.as_mut()));
+
+        notify.notify_one();
+        drop(a);
+
+        assert!(matches!(
+            b.as_mut().poll(&mut Context::from_waker(Waker::noop())),
+            Poll::Ready(())
+        ));
+    }
+
+    #[test]
+    fn cross_thread() {
+        let notify = Arc::new(Notify::new());
+
+        let t1 = {
+            let notify = notify.clone();
+            thread::spawn(move || {
+                Executor::block_on(async move {
+                    for _ in 0..5 {
+                        notify.notified().await;
+                    }
+                })
+            })
+        };
+
+        for _ in 0..5 {
+            thread::sleep(Duration::from_millis(10));
+            notify.notify_one();
+        }
+
+        t1.join().unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! ### Async Lazy Initialization
+//!
+//! This module provides [OnceCell], a cell which is written to at most once
+//! and whose initializer may be asynchronous. This is useful for shared
+//! resources which are expensive to set up and should only be created when
+//! first needed, such as a configuration file or a connection to an upstream
+//! service.
+//!
+//! When several tasks call [OnceCell::get_or_init] on an uninitialized cell at
+//! the same time, only one of them runs its initializer; the others yield
+//! until the value is available. If the initializer fails (see
+//! [OnceCell::get_or_try_init]) or its task is dropped, the next waiting task
+//! runs its own initializer instead.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::once_cell::OnceCell;
+//! use trale::futures::timer::Timer;
+//! use std::time::Duration;
+//!
+//! static CONFIG: OnceCell<String> = OnceCell::new();
+//!
+//! async fn config() -> &'static str {
+//!     CONFIG
+//!         .get_or_init(|| async {
+//!             Timer::sleep(Duration::from_millis(10)).unwrap().await;
+//!             String::from("loaded")
+//!         })
+//!         .await
+//! }
+//!
+//! let tasks: Vec<_> = (0..3).map(|_| Executor::spawn(config())).collect();
+//!
+//! Executor::run();
+//!
+//! for task in tasks {
+//!     assert_eq!(task.join(), "loaded");
+//! }
+//! ```
+use std::{future::Future, sync::OnceLock};
+
+use super::semaphore::Semaphore;
+
+/// A cell which can be asynchronously initialized once.
+///
+/// See the [module-level documentation](self) for more information.
+pub struct OnceCell<T> {
+    value: OnceLock<T>,
+    /// Held by the task that is currently running an initializer.
+    init: Semaphore,
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> OnceCell<T> {
+    /// Create a new, uninitialized cell.
+    pub const fn new() -> Self {
+        Self {
+            value: OnceLock::new(),
+            init: Semaphore::new(1),
+        }
+    }
+
+    /// Returns the value if the cell has been initialized.
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// Returns `true` if the cell has been initialized.
+    pub fn initialized(&self) -> bool {
+        self.get().is_some()
+    }
+
+    /// Initialize the cell with `value`.
+    ///
+    /// If the cell is already initialized, or another task is currently
+    /// initializing it, the value is handed back in the `Err` variant.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let Ok(_permit) = self.init.try_acquire(1) else {
+            return Err(value);
+        };
+
+        self.value.set(value)
+    }
+
+    /// Return the value of the cell, initializing it with the future returned
+    /// by `f` if needed.
+    ///
+    /// If another task is already initializing the cell, this waits for it
+    /// to finish rather than calling `f`.
+    pub async fn get_or_init<F, Fut>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        match self
+            .get_or_try_init(|| async { Ok::<T, std::convert::Infallible>(f().await) })
+            .await
+        {
+            Ok(value) => value,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Return the value of the cell, initializing it with the future returned
+    /// by `f` if needed.
+    ///
+    /// If `f` fails, the cell is left uninitialized, the error is returned
+    /// and the next waiting task (if any) gets to run its initializer.
+    pub async fn get_or_try_init<F, Fut, E>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+
+        // The semaphore is never closed.
+        let _permit = self.init.acquire(1).await.unwrap();
+
+        // Another task may have finished initializing the cell while we were
+        // waiting.
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+
+        let value = f().await?;
+
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Take the value out of the cell, leaving it uninitialized.
+    pub fn take(&mut self) -> Option<T> {
+        self.value.take()
+    }
+
+    /// Consume the cell, returning its value if it was initialized.
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OnceCell;
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn single_initializer() {
+        let cell = Arc::new(OnceCell::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..5)
+            .map(|i| {
+                let cell = cell.clone();
+                let calls = calls.clone();
+                Executor::spawn(async move {
+                    *cell
+                        .get_or_init(|| async {
+                            calls.fetch_add(1, Ordering::Relaxed);
+                            Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                            i
+                        })
+                        .await
+                })
+            })
+            .collect();
+
+        Executor::run();
+
+        for task in tasks {
+            assert_eq!(task.join(), 0);
+        }
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn failed_init_is_retried() {
+        let cell = OnceCell::new();
+
+        Executor::block_on(async move {
+            let res = cell.get_or_try_init(|| async { Err("boom") }).await;
+            assert_eq!(res, Err("boom"));
+            assert!(!cell.initialized());
+
+            let res = cell.get_or_try_init(|| async { Ok::<_, ()>(5) }).await;
+            assert_eq!(res, Ok(&5));
+            assert_eq!(cell.set(6), Err(6));
+        });
+    }
+
+    #[test]
+    fn set_and_take() {
+        let mut cell = OnceCell::new();
+
+        assert_eq!(cell.get(), None);
+        cell.set(1).unwrap();
+        assert_eq!(cell.get(), Some(&1));
+        assert_eq!(cell.take(), Some(1));
+        assert_eq!(cell.into_inner(), None);
+    }
+
+    #[test]
+    fn cross_thread() {
+        let cell = Arc::new(OnceCell::new());
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let cell = cell.clone();
+                thread::spawn(move || {
+                    Executor::block_on(async move {
+                        *cell
+                            .get_or_init(|| async move {
+                                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                                i
+                            })
+                            .await
+                    })
+                })
+            })
+            .collect();
+
+        let values: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+        assert!(values.iter().all(|v| *v == values[0]));
+    }
+}
@@ -0,0 +1,638 @@
+//! ### Async Reader-Writer Locks
+//!
+//! This module provides a **cross-thread, non-blocking reader-writer lock**.
+//! Like [Mutex](super::mutex::Mutex), attempting to acquire an
+//! [RwLock] that is unavailable does not block the executor; instead the task
+//! yields and is resumed once the lock can be granted. Unlike a mutex, any
+//! number of readers may hold the lock at once, whereas a writer has exclusive
+//! access.
+//!
+//! The lock is *writer-preferring*: once a writer is waiting, new readers
+//! queue up behind it rather than continuing to share the lock, so a steady
+//! stream of readers can't starve writers. Waiting tasks are granted the lock
+//! in the order in which they arrived.
+//!
+//! An *upgradable* read lock can be used by a task which needs to inspect the
+//! protected value before deciding whether to modify it. It shares the lock
+//! with ordinary readers but excludes writers and other upgradable readers, so
+//! that it can be atomically upgraded to a write lock.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::rwlock::{RwLock, RwLockUpgradableReadGuard};
+//! use std::sync::Arc;
+//!
+//! let cfg = Arc::new(RwLock::new(String::from("v1")));
+//!
+//! for _ in 0..4 {
+//!     let cfg = cfg.clone();
+//!     Executor::spawn(async move {
+//!         let cfg = cfg.read().await;
+//!         assert!(cfg.starts_with('v'));
+//!     });
+//! }
+//!
+//! {
+//!     let cfg = cfg.clone();
+//!     Executor::spawn(async move {
+//!         let cfg = cfg.upgradable_read().await;
+//!         if *cfg == "v1" {
+//!             let mut cfg = RwLockUpgradableReadGuard::upgrade(cfg).await;
+//!             *cfg = String::from("v2");
+//!         }
+//!     });
+//! }
+//!
+//! Executor::run();
+//! assert_eq!(*cfg.try_read().unwrap(), "v2");
+//! ```
+use std::{
+    cell::UnsafeCell,
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll, Waker},
+};
+
+use super::waiters::WaitList;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    UpgradableRead,
+    Write,
+}
+
+struct State {
+    readers: usize,
+    writer: bool,
+    upgradable: bool,
+    /// The holder of the upgradable read lock is waiting for the remaining
+    /// readers to release the lock.
+    upgrade: Option<Waker>,
+    writers_waiting: usize,
+    waiters: WaitList<Access>,
+}
+
+impl State {
+    /// Whether `access` could be granted to a task that is waiting in the
+    /// queue.
+    fn can_grant(&self, access: Access) -> bool {
+        match access {
+            Access::Read => !self.writer && self.upgrade.is_none(),
+            Access::UpgradableRead => !self.writer && !self.upgradable && self.upgrade.is_none(),
+            Access::Write => !self.writer && !self.upgradable && self.readers == 0,
+        }
+    }
+
+    /// Whether `access` can be granted to a newly arrived task without
+    /// overtaking any task that is already waiting.
+    fn can_acquire(&self, access: Access) -> bool {
+        match access {
+            Access::Read | Access::UpgradableRead => {
+                self.writers_waiting == 0 && self.can_grant(access)
+            }
+            Access::Write => self.waiters.is_empty() && self.can_grant(access),
+        }
+    }
+
+    /// Convert the upgradable read lock into a write lock if there are no
+    /// other readers.
+    fn try_upgrade(&mut self) -> bool {
+        if self.readers != 0 {
+            return false;
+        }
+
+        self.upgradable = false;
+        self.upgrade = None;
+        self.writer = true;
+
+        true
+    }
+
+    fn grant(&mut self, access: Access) {
+        match access {
+            Access::Read => self.readers += 1,
+            Access::UpgradableRead => self.upgradable = true,
+            Access::Write => self.writer = true,
+        }
+    }
+
+    fn release(&mut self, access: Access) {
+        match access {
+            Access::Read => {
+                self.readers -= 1;
+
+                if self.readers == 0 {
+                    if let Some(waker) = self.upgrade.take() {
+                        waker.wake();
+                    }
+                }
+            }
+            Access::UpgradableRead => self.upgradable = false,
+            Access::Write => self.writer = false,
+        }
+
+        self.wake_waiters();
+    }
+
+    /// Hand the lock to waiting tasks, in order, for as long as it can be
+    /// granted.
+    fn wake_waiters(&mut self) {
+        while let Some(&access) = self.waiters.front() {
+            if !self.can_grant(access) {
+                break;
+            }
+
+            self.grant(access);
+
+            if access == Access::Write {
+                self.writers_waiting -= 1;
+            }
+
+            self.waiters.notify_front();
+        }
+    }
+}
+
+/// An async-aware reader-writer lock.
+///
+/// See the [module-level documentation](self) for more information.
+pub struct RwLock<T> {
+    state: Mutex<State>,
+    obj: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+/// Shared read access to the value protected by an [RwLock].
+///
+/// Returned by [RwLock::read] and [RwLock::try_read]; the lock is released
+/// when the guard is dropped.
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/// Exclusive write access to the value protected by an [RwLock].
+///
+/// Returned by [RwLock::write], [RwLock::try_write] and
+/// [RwLockUpgradableReadGuard::upgrade]; the lock is released when the guard
+/// is dropped.
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/// Shared read access which can be upgraded to exclusive write access.
+///
+/// Returned by [RwLock::upgradable_read] and [RwLock::try_upgradable_read];
+/// the lock is released when the guard is dropped.
+pub struct RwLockUpgradableReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> RwLock<T> {
+    /// Create a new reader-writer lock protecting `obj`.
+    pub fn new(obj: T) -> Self {
+        Self {
+            state: Mutex::new(State {
+                readers: 0,
+                writer: false,
+                upgradable: false,
+                upgrade: None,
+                writers_waiting: 0,
+                waiters: WaitList::new(),
+            }),
+            obj: UnsafeCell::new(obj),
+        }
+    }
+
+    /// Acquire shared read access to the lock.
+    ///
+    /// If a writer holds, or is waiting for, the lock then the task is put to
+    /// sleep until read access can be granted.
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.acquire(Access::Read).await;
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Acquire exclusive write access to the lock.
+    ///
+    /// If the lock is held by any other task then the task is put to sleep
+    /// until all readers and writers that were granted, or were waiting for,
+    /// the lock before it have released it.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.acquire(Access::Write).await;
+        RwLockWriteGuard { lock: self }
+    }
+
+    /// Acquire upgradable read access to the lock.
+    ///
+    /// This shares the lock with ordinary readers but excludes writers and
+    /// other upgradable readers. See [RwLockUpgradableReadGuard::upgrade].
+    pub async fn upgradable_read(&self) -> RwLockUpgradableReadGuard<'_, T> {
+        self.acquire(Access::UpgradableRead).await;
+        RwLockUpgradableReadGuard { lock: self }
+    }
+
+    /// Attempt to acquire shared read access without waiting.
+    ///
+    /// Returns `None` if read access can't be granted immediately.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.try_acquire(Access::Read)
+            .then(|| RwLockReadGuard { lock: self })
+    }
+
+    /// Attempt to acquire exclusive write access without waiting.
+    ///
+    /// Returns `None` if write access can't be granted immediately.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.try_acquire(Access::Write)
+            .then(|| RwLockWriteGuard { lock: self })
+    }
+
+    /// Attempt to acquire upgradable read access without waiting.
+    ///
+    /// Returns `None` if upgradable read access can't be granted immediately.
+    pub fn try_upgradable_read(&self) -> Option<RwLockUpgradableReadGuard<'_, T>> {
+        self.try_acquire(Access::UpgradableRead)
+            .then(|| RwLockUpgradableReadGuard { lock: self })
+    }
+
+    /// Consume the lock, returning the protected value.
+    pub fn into_inner(self) -> T {
+        self.obj.into_inner()
+    }
+
+    fn try_acquire(&self, access: Access) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        if state.can_acquire(access) {
+            state.grant(access);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn acquire(&self, access: Access) -> Acquire<'_, T> {
+        Acquire {
+            lock: self,
+            access,
+            key: None,
+        }
+    }
+
+    fn release(&self, access: Access) {
+        self.state.lock().unwrap().release(access);
+    }
+}
+
+/// A future which resolves once `access` has been granted to the task.
+struct Acquire<'a, T> {
+    lock: &'a RwLock<T>,
+    access: Access,
+    key: Option<usize>,
+}
+
+impl<T> Future for Acquire<'_, T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.lock.state.lock().unwrap();
+
+        match self.key {
+            None if state.can_acquire(self.access) => {
+                state.grant(self.access);
+                Poll::Ready(())
+            }
+            None => {
+                if self.access == Access::Write {
+                    state.writers_waiting += 1;
+                }
+
+                let key = state.waiters.push(self.access, cx.waker());
+                drop(state);
+                self.key = Some(key);
+                Poll::Pending
+            }
+            Some(key) if state.waiters.is_notified(key) => {
+                state.waiters.remove(key);
+                drop(state);
+                self.key = None;
+                Poll::Ready(())
+            }
+            Some(key) => {
+                state.waiters.register(key, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Acquire<'_, T> {
+    fn drop(&mut self) {
+        let Some(key) = self.key else {
+            return;
+        };
+
+        let mut state = self.lock.state.lock().unwrap();
+
+        match state.waiters.remove(key) {
+            // The lock was handed to us but we'll never use it.
+            (access, true) => state.release(access),
+            (access, false) => {
+                if access == Access::Write {
+                    state.writers_waiting -= 1;
+                }
+
+                // A departing writer may have been holding back readers.
+                state.wake_waiters();
+            }
+        }
+    }
+}
+
+impl<'a, T> RwLockUpgradableReadGuard<'a, T> {
+    /// Upgrade to exclusive write access.
+    ///
+    /// New readers are prevented from acquiring the lock and the task is put
+    /// to sleep until all existing readers have released it.
+    pub async fn upgrade(this: Self) -> RwLockWriteGuard<'a, T> {
+        Upgrade { guard: Some(this) }.await
+    }
+
+    /// Attempt to upgrade to exclusive write access without waiting.
+    ///
+    /// If there are other readers, the upgradable guard is returned as the
+    /// error.
+    pub fn try_upgrade(this: Self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        if this.lock.state.lock().unwrap().try_upgrade() {
+            Ok(this.into_write_guard())
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Convert into a write guard once the upgrade has taken place.
+    fn into_write_guard(self) -> RwLockWriteGuard<'a, T> {
+        let lock = self.lock;
+        std::mem::forget(self);
+        RwLockWriteGuard { lock }
+    }
+}
+
+struct Upgrade<'a, T> {
+    guard: Option<RwLockUpgradableReadGuard<'a, T>>,
+}
+
+impl<'a, T> Future for Upgrade<'a, T> {
+    type Output = RwLockWriteGuard<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let lock = self.guard.as_ref().unwrap().lock;
+        let mut state = lock.state.lock().unwrap();
+
+        if state.try_upgrade() {
+            drop(state);
+            Poll::Ready(self.guard.take().unwrap().into_write_guard())
+        } else {
+            state.upgrade = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for Upgrade<'_, T> {
+    fn drop(&mut self) {
+        if let Some(guard) = &self.guard {
+            // Allow readers to make progress again; the upgradable guard
+            // itself is released when it is dropped.
+            let mut state = guard.lock.state.lock().unwrap();
+            state.upgrade = None;
+            state.wake_waiters();
+        }
+    }
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.obj.get() }
+    }
+}
+
+impl<T> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.obj.get() }
+    }
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.obj.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.obj.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release(Access::Read);
+    }
+}
+
+impl<T> Drop for RwLockUpgradableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release(Access::UpgradableRead);
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release(Access::Write);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RwLock, RwLockUpgradableReadGuard};
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{future::Future, sync::Arc, thread, time::Duration};
+
+    #[test]
+    fn concurrent_readers() {
+        let lock = RwLock::new(5);
+
+        let r1 = lock.try_read().unwrap();
+        let r2 = lock.try_read().unwrap();
+
+        assert_eq!(*r1 + *r2, 10);
+        assert!(lock.try_write().is_none());
+
+        drop(r1);
+        drop(r2);
+
+        assert!(lock.try_write().is_some());
+    }
+
+    #[test]
+    fn writer_preference() {
+        let lock = Arc::new(RwLock::new(Vec::new()));
+
+        Executor::block_on({
+            let lock = lock.clone();
+            async move {
+                let read = lock.read().await;
+
+                let writer = {
+                    let lock = lock.clone();
+                    Executor::spawn(async move { lock.write().await.push("write") })
+                };
+
+                Timer::sleep(Duration::from_millis(50)).unwrap().await;
+
+                // A writer is waiting, so new readers must queue behind it.
+                assert!(lock.try_read().is_none());
+
+                let reader = {
+                    let lock = lock.clone();
+                    Executor::spawn(async move {
+                        let v = lock.read().await;
+                        assert_eq!(*v, ["write"]);
+                    })
+                };
+
+                Timer::sleep(Duration::from_millis(50)).unwrap().await;
+                drop(read);
+
+                writer.await;
+                reader.await;
+            }
+        });
+
+        assert_eq!(Arc::into_inner(lock).unwrap().into_inner(), ["write"]);
+    }
+
+    #[test]
+    fn upgrade_waits_for_readers() {
+        let lock = Arc::new(RwLock::new(0));
+
+        Executor::block_on({
+            let lock = lock.clone();
+            async move {
+                let reader = {
+                    let lock = lock.clone();
+                    Executor::spawn(async move {
+                        let _read = lock.read().await;
+                        Timer::sleep(Duration::from_millis(100)).unwrap().await;
+                    })
+                };
+
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+
+                let upgradable = lock.upgradable_read().await;
+
+                assert!(lock.try_upgradable_read().is_none());
+
+                let Err(upgradable) = RwLockUpgradableReadGuard::try_upgrade(upgradable) else {
+                    panic!("upgrade should fail whilst there are readers");
+                };
+
+                let checker = {
+                    let lock = lock.clone();
+                    Executor::spawn(async move {
+                        Timer::sleep(Duration::from_millis(50)).unwrap().await;
+                        // The pending upgrade holds back new readers.
+                        assert!(lock.try_read().is_none());
+                    })
+                };
+
+                let mut write = RwLockUpgradableReadGuard::upgrade(upgradable).await;
+                *write += 1;
+                drop(write);
+
+                reader.await;
+                checker.await;
+            }
+        });
+
+        assert_eq!(*lock.try_read().unwrap(), 1);
+    }
+
+    #[test]
+    fn dropped_waiter_releases() {
+        let lock = Arc::new(RwLock::new(0));
+
+        Executor::block_on({
+            let lock = lock.clone();
+            async move {
+                let read = lock.read().await;
+
+                let writer = {
+                    let lock = lock.clone();
+                    Executor::spawn(async move {
+                        let write = lock.write();
+                        let timeout = Timer::sleep(Duration::from_millis(50)).unwrap();
+                        // Race the writer against a timeout, dropping the
+                        // write future when the timeout wins.
+                        let mut write = std::pin::pin!(write);
+                        let mut timeout = std::pin::pin!(timeout);
+                        std::future::poll_fn(|cx| {
+                            if write.as_mut().poll(cx).is_ready() {
+                                panic!("write should not have been granted");
+                            }
+                            timeout.as_mut().poll(cx)
+                        })
+                        .await;
+                    })
+                };
+
+                writer.await;
+
+                // The abandoned writer no longer holds back readers.
+                assert!(lock.try_read().is_some());
+                drop(read);
+                assert!(lock.try_write().is_some());
+            }
+        });
+    }
+
+    #[test]
+    fn cross_thread() {
+        let lock = Arc::new(RwLock::new(0));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    Executor::block_on(async move {
+                        for _ in 0..50 {
+                            *lock.write().await += 1;
+                            let v = lock.read().await;
+                            assert!(*v > 0);
+                        }
+                    })
+                })
+            })
+            .collect();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        assert_eq!(*lock.try_read().unwrap(), 200);
+    }
+}
@@ -0,0 +1,497 @@
+//! ### Async Counting Semaphores
+//!
+//! This module provides a **cross-thread, non-blocking counting semaphore**.
+//! A [Semaphore] holds a number of permits; tasks acquire one or more permits
+//! before performing some operation and return them once finished, which
+//! makes semaphores a natural way of limiting the number of concurrent
+//! connections, file operations and so on.
+//!
+//! When not enough permits are available, the acquiring task yields until
+//! they are returned. Waiting tasks are served in strict FIFO order: a task
+//! which requests many permits is never overtaken by later tasks which request
+//! fewer, so it can't be starved.
+//!
+//! Permits are returned to the semaphore when the [SemaphorePermit] (or
+//! [OwnedSemaphorePermit]) that represents them is dropped.
+//!
+//! #### Example
+//!
+//! ```rust
+//! use trale::task::Executor;
+//! use trale::futures::semaphore::Semaphore;
+//! use trale::futures::timer::Timer;
+//! use std::sync::Arc;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::time::Duration;
+//!
+//! let sem = Arc::new(Semaphore::new(2));
+//! let active = Arc::new(AtomicUsize::new(0));
+//!
+//! for _ in 0..6 {
+//!     let sem = sem.clone();
+//!     let active = active.clone();
+//!     Executor::spawn(async move {
+//!         let _permit = sem.acquire(1).await.unwrap();
+//!         assert!(active.fetch_add(1, Ordering::Relaxed) < 2);
+//!         Timer::sleep(Duration::from_millis(10)).unwrap().await;
+//!         active.fetch_sub(1, Ordering::Relaxed);
+//!     });
+//! }
+//!
+//! Executor::run();
+//! assert_eq!(sem.available_permits(), 2);
+//! ```
+use std::{
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use super::waiters::WaitList;
+
+struct Request {
+    permits: usize,
+    granted: bool,
+}
+
+struct State {
+    permits: usize,
+    closed: bool,
+    waiters: WaitList<Request>,
+}
+
+impl State {
+    fn try_acquire(&mut self, n: usize) -> Result<(), TryAcquireError> {
+        if self.closed {
+            return Err(TryAcquireError::Closed);
+        }
+
+        if !self.waiters.is_empty() || self.permits < n {
+            return Err(TryAcquireError::NoPermits);
+        }
+
+        self.permits -= n;
+
+        Ok(())
+    }
+
+    fn release(&mut self, n: usize) {
+        self.permits += n;
+
+        while let Some(req) = self.waiters.front_mut() {
+            if req.permits > self.permits {
+                break;
+            }
+
+            self.permits -= req.permits;
+            req.granted = true;
+            self.waiters.notify_front();
+        }
+    }
+}
+
+/// An async-aware counting semaphore.
+///
+/// See the [module-level documentation](self) for more information.
+pub struct Semaphore {
+    state: Mutex<State>,
+}
+
+/// Permits acquired from a [Semaphore].
+///
+/// The permits are returned to the semaphore when this object is dropped.
+pub struct SemaphorePermit<'a> {
+    sem: &'a Semaphore,
+    permits: usize,
+}
+
+/// Permits acquired from a [Semaphore] held in an [Arc].
+///
+/// Unlike [SemaphorePermit], this type does not borrow the semaphore and so
+/// can be moved into spawned tasks. The permits are returned to the semaphore
+/// when this object is dropped.
+pub struct OwnedSemaphorePermit {
+    sem: Arc<Semaphore>,
+    permits: usize,
+}
+
+/// The error returned by [Semaphore::acquire] when the semaphore has been
+/// closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcquireError;
+
+/// The error returned by [Semaphore::try_acquire].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryAcquireError {
+    /// The semaphore has been closed.
+    Closed,
+    /// There are not enough permits available, or other tasks are waiting
+    /// for permits.
+    NoPermits,
+}
+
+impl Display for AcquireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "semaphore closed")
+    }
+}
+
+impl std::error::Error for AcquireError {}
+
+impl Display for TryAcquireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryAcquireError::Closed => write!(f, "semaphore closed"),
+            TryAcquireError::NoPermits => write!(f, "no permits available"),
+        }
+    }
+}
+
+impl std::error::Error for TryAcquireError {}
+
+impl Semaphore {
+    /// Create a new semaphore with `permits` initial permits.
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                permits,
+                closed: false,
+                waiters: WaitList::new(),
+            }),
+        }
+    }
+
+    /// Acquire `n` permits.
+    ///
+    /// If there are not enough permits available, or other tasks are already
+    /// waiting for permits, the task is put to sleep until the permits have
+    /// been granted to it. Returns an error if the semaphore is closed before
+    /// the permits could be acquired.
+    pub async fn acquire(&self, n: usize) -> Result<SemaphorePermit<'_>, AcquireError> {
+        self.acquire_permits(n).await?;
+
+        Ok(SemaphorePermit {
+            sem: self,
+            permits: n,
+        })
+    }
+
+    /// Acquire `n` permits from a semaphore held in an [Arc].
+    ///
+    /// See [Semaphore::acquire].
+    pub async fn acquire_owned(
+        self: Arc<Self>,
+        n: usize,
+    ) -> Result<OwnedSemaphorePermit, AcquireError> {
+        self.acquire_permits(n).await?;
+
+        Ok(OwnedSemaphorePermit {
+            sem: self,
+            permits: n,
+        })
+    }
+
+    /// Attempt to acquire `n` permits without waiting.
+    ///
+    /// This fails if the permits are not available immediately, or if other
+    /// tasks are waiting for permits.
+    pub fn try_acquire(&self, n: usize) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.state.lock().unwrap().try_acquire(n)?;
+
+        Ok(SemaphorePermit {
+            sem: self,
+            permits: n,
+        })
+    }
+
+    /// Attempt to acquire `n` permits from a semaphore held in an [Arc]
+    /// without waiting.
+    ///
+    /// See [Semaphore::try_acquire].
+    pub fn try_acquire_owned(
+        self: Arc<Self>,
+        n: usize,
+    ) -> Result<OwnedSemaphorePermit, TryAcquireError> {
+        self.state.lock().unwrap().try_acquire(n)?;
+
+        Ok(OwnedSemaphorePermit {
+            sem: self,
+            permits: n,
+        })
+    }
+
+    /// Add `n` new permits to the semaphore, waking any tasks that can now
+    /// acquire their permits.
+    pub fn add_permits(&self, n: usize) {
+        self.state.lock().unwrap().release(n);
+    }
+
+    /// The number of permits that are currently available.
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().permits
+    }
+
+    /// Close the semaphore.
+    ///
+    /// Any tasks waiting for permits are woken and fail with an
+    /// [AcquireError], as do all future attempts to acquire permits. Permits
+    /// that have already been acquired are unaffected.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        state.closed = true;
+
+        while state.waiters.notify_front() {}
+    }
+
+    /// Returns `true` if [Semaphore::close] has been called.
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    fn acquire_permits(&self, n: usize) -> Acquire<'_> {
+        Acquire {
+            sem: self,
+            permits: n,
+            key: None,
+        }
+    }
+}
+
+/// A future which resolves once `permits` have been granted to the task.
+struct Acquire<'a> {
+    sem: &'a Semaphore,
+    permits: usize,
+    key: Option<usize>,
+}
+
+impl Future for Acquire<'_> {
+    type Output = Result<(), AcquireError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.sem.state.lock().unwrap();
+
+        match self.key {
+            None => match state.try_acquire(self.permits) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(TryAcquireError::Closed) => Poll::Ready(Err(AcquireError)),
+                Err(TryAcquireError::NoPermits) => {
+                    let req = Request {
+                        permits: self.permits,
+                        granted: false,
+                    };
+                    let key = state.waiters.push(req, cx.waker());
+                    drop(state);
+                    self.key = Some(key);
+                    Poll::Pending
+                }
+            },
+            Some(key) if state.waiters.is_notified(key) => {
+                let (req, _) = state.waiters.remove(key);
+                drop(state);
+                self.key = None;
+
+                if req.granted {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Ready(Err(AcquireError))
+                }
+            }
+            Some(key) => {
+                state.waiters.register(key, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        let Some(key) = self.key else {
+            return;
+        };
+
+        let mut state = self.sem.state.lock().unwrap();
+
+        match state.waiters.remove(key) {
+            // Hand back permits that were granted but will never be used.
+            (req, true) if req.granted => state.release(req.permits),
+            // A large request at the head of the queue may have been holding
+            // back smaller ones.
+            _ => state.release(0),
+        }
+    }
+}
+
+impl SemaphorePermit<'_> {
+    /// The number of permits held.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Consume the permit without returning it to the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl OwnedSemaphorePermit {
+    /// The number of permits held.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Consume the permit without returning it to the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits != 0 {
+            self.sem.add_permits(self.permits);
+        }
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        if self.permits != 0 {
+            self.sem.add_permits(self.permits);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AcquireError, Semaphore, TryAcquireError};
+    use crate::{futures::timer::Timer, task::Executor};
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn try_acquire() {
+        let sem = Semaphore::new(3);
+
+        let p1 = sem.try_acquire(2).unwrap();
+        assert_eq!(sem.available_permits(), 1);
+        assert_eq!(sem.try_acquire(2).err(), Some(TryAcquireError::NoPermits));
+
+        drop(p1);
+        assert_eq!(sem.available_permits(), 3);
+
+        sem.try_acquire(3).unwrap().forget();
+        assert_eq!(sem.available_permits(), 0);
+
+        sem.add_permits(1);
+        sem.close();
+        assert_eq!(sem.try_acquire(1).err(), Some(TryAcquireError::Closed));
+    }
+
+    #[test]
+    fn fifo_order() {
+        let sem = Arc::new(Semaphore::new(0));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        Executor::block_on({
+            let sem = sem.clone();
+            let order = order.clone();
+            async move {
+                let mut tasks = Vec::new();
+
+                // A large request followed by small ones; the small requests
+                // must not overtake it.
+                for (i, n) in [3, 1, 1].into_iter().enumerate() {
+                    let sem = sem.clone();
+                    let order = order.clone();
+                    tasks.push(Executor::spawn(async move {
+                        let _permit = sem.acquire(n).await.unwrap();
+                        order.lock().unwrap().push(i);
+                    }));
+                    Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                }
+
+                sem.add_permits(1);
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                assert!(order.lock().unwrap().is_empty());
+
+                sem.add_permits(2);
+
+                for task in tasks {
+                    task.await;
+                }
+            }
+        });
+
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2]);
+        assert_eq!(sem.available_permits(), 3);
+    }
+
+    #[test]
+    fn owned_permit_moves_into_task() {
+        let sem = Arc::new(Semaphore::new(1));
+
+        Executor::block_on({
+            let sem = sem.clone();
+            async move {
+                let permit = sem.clone().acquire_owned(1).await.unwrap();
+
+                let task = Executor::spawn(async move {
+                    Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                    drop(permit);
+                });
+
+                let _permit = sem.acquire(1).await.unwrap();
+                task.await;
+            }
+        });
+    }
+
+    #[test]
+    fn close_wakes_waiters() {
+        let sem = Arc::new(Semaphore::new(0));
+
+        Executor::block_on({
+            let sem = sem.clone();
+            async move {
+                let task = {
+                    let sem = sem.clone();
+                    Executor::spawn(async move { sem.acquire(1).await.err() })
+                };
+
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                sem.close();
+
+                assert_eq!(task.await, Some(AcquireError));
+            }
+        });
+    }
+
+    #[test]
+    fn cross_thread() {
+        let sem = Arc::new(Semaphore::new(0));
+
+        let t1 = {
+            let sem = sem.clone();
+            thread::spawn(move || {
+                Executor::block_on(async move {
+                    sem.acquire(10).await.unwrap().forget();
+                })
+            })
+        };
+
+        for _ in 0..10 {
+            thread::sleep(Duration::from_millis(5));
+            sem.add_permits(1);
+        }
+
+        t1.join().unwrap();
+        assert_eq!(sem.available_permits(), 0);
+    }
+}
@@ -35,7 +35,8 @@ use std::{
     net::{SocketAddr, ToSocketAddrs},
     os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
     pin::Pin,
-    task::{Context, Poll},
+    ptr::null_mut,
+    task::{ready, Context, Poll},
 };
 
 use io_uring::{opcode, types};
@@ -46,7 +47,7 @@ use crate::reactor::{MultishotReactorIo, Reactor, ReactorIo};
 
 use super::{
     read::{AsyncRead, AsyncReader},
-    sock_addr::CSockAddr,
+    sock_addr::{CSockAddr, CSockAddrs},
     write::{AsyncWrite, AsyncWriter},
 };
 
@@ -56,7 +57,14 @@ use super::{
 /// accept connections on the specified address.
 pub struct TcpListener {
     inner: OwnedFd,
-    io: MultishotReactorIo,
+    accept: Acceptor,
+}
+
+/// How connections are accepted. Multishot accept requires Linux 5.19; on
+/// older kernels a oneshot accept is submitted for each connection instead.
+enum Acceptor {
+    Multishot(MultishotReactorIo),
+    Oneshot(ReactorIo),
 }
 
 fn mk_sock(addr: &SocketAddr) -> std::io::Result<OwnedFd> {
@@ -102,7 +110,7 @@ impl TcpListener {
                 0 => {
                     return Ok(Self {
                         inner: sock,
-                        io: Reactor::new_multishot_io(),
+                        accept: Acceptor::Multishot(Reactor::new_multishot_io()),
                     })
                 }
                 _ => unreachable!("listen() cannot return a value other than 0 or -1"),
@@ -111,6 +119,33 @@ impl TcpListener {
 
         Err(last_err)
     }
+
+    /// Return the local address that this listener is bound to.
+    ///
+    /// This is useful after binding to port 0, to find out which port was
+    /// assigned by the OS.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        let mut addr = CSockAddr {
+            addr: unsafe { std::mem::zeroed() },
+            len: std::mem::size_of::<CSockAddrs>(),
+        };
+        let mut len = addr.len as libc::socklen_t;
+
+        if unsafe {
+            libc::getsockname(
+                self.inner.as_raw_fd(),
+                &mut addr.addr as *mut _ as *mut _,
+                &mut len,
+            )
+        } == -1
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        addr.len = len as usize;
+
+        (&addr).try_into()
+    }
 }
 
 impl Stream for TcpListener {
@@ -118,21 +153,43 @@ impl Stream for TcpListener {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
+        let fd = types::Fd(this.inner.as_raw_fd());
 
-        this.io
-            .submit_or_get_result(|| {
-                (
-                    opcode::AcceptMulti::new(types::Fd(this.inner.as_raw_fd())).build(),
-                    cx.waker().clone(),
-                )
-            })
-            .map(|x| {
-                x.map(|x| {
-                    x.map(|fd| TcpStream {
-                        inner: unsafe { OwnedFd::from_raw_fd(fd) },
-                    })
+        let res = loop {
+            match &mut this.accept {
+                Acceptor::Multishot(io) => {
+                    match io.submit_or_get_result(|| {
+                        (opcode::AcceptMulti::new(fd).build(), cx.waker().clone())
+                    }) {
+                        // The kernel doesn't support multishot accept.
+                        Poll::Ready(Some(Err(e))) if e.raw_os_error() == Some(libc::EINVAL) => {
+                            this.accept = Acceptor::Oneshot(Reactor::new_io());
+                        }
+                        res => break res,
+                    }
+                }
+                Acceptor::Oneshot(io) => {
+                    let res = ready!(io.submit_or_get_result(|| {
+                        (
+                            opcode::Accept::new(fd, null_mut(), null_mut()).build(),
+                            cx.waker().clone(),
+                        )
+                    }));
+
+                    *io = Reactor::new_io();
+
+                    break Poll::Ready(Some(res));
+                }
+            }
+        };
+
+        res.map(|x| {
+            x.map(|x| {
+                x.map(|fd| TcpStream {
+                    inner: unsafe { OwnedFd::from_raw_fd(fd) },
                 })
             })
+        })
     }
 }
 
@@ -203,7 +260,7 @@ impl AsyncRead for TcpStream {
             fd: self.inner.as_fd(),
             io: Reactor::new_io(),
             buf,
-            seekable: false,
+            offset: 0,
         }
     }
 }
@@ -214,7 +271,62 @@ impl AsyncWrite for TcpStream {
             fd: self.inner.as_fd(),
             io: Reactor::new_io(),
             buf,
-            seekable: false,
+            offset: 0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Acceptor, TcpListener};
+    use crate::{
+        futures::{read::AsyncRead, write::AsyncWrite},
+        reactor::Reactor,
+        task::Executor,
+    };
+    use std::{
+        io::{Read, Write},
+        net::{Ipv4Addr, TcpStream},
+        thread,
+    };
+    use tokio_stream::StreamExt;
+
+    fn echo_once(mut listener: TcpListener) {
+        let port = listener.local_addr().unwrap().port();
+        let client = thread::spawn(move || {
+            let mut sock = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+            let mut buf = [0];
+            sock.write_all(b"x").unwrap();
+            sock.read_exact(&mut buf).unwrap();
+            buf
+        });
+
+        Executor::block_on(async move {
+            let mut sock = listener.next().await.unwrap().unwrap();
+            let mut buf = [0];
+            sock.read(&mut buf).await.unwrap();
+            sock.write(&buf).await.unwrap();
+
+            // Wait for the client to hang up first, so that the port isn't
+            // left in TIME_WAIT for the next test run.
+            assert_eq!(sock.read(&mut buf).await.unwrap(), 0);
+        });
+
+        assert_eq!(&client.join().unwrap(), b"x");
+    }
+
+    #[test]
+    fn accept_multishot() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+        echo_once(listener);
+    }
+
+    #[test]
+    fn accept_oneshot_fallback() {
+        let mut listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        listener.accept = Acceptor::Oneshot(Reactor::new_io());
+
+        echo_once(listener);
+    }
+}
@@ -0,0 +1,154 @@
+//! FIFO queues of waiting tasks.
+//!
+//! This is the building block for trale's synchronisation primitives. Each
+//! primitive keeps a [WaitList] alongside its own state, protected by a
+//! `std::sync::Mutex`. A task that can't make progress pushes itself onto the
+//! list and the releasing side notifies waiters in the order in which they
+//! arrived, typically after handing them whatever they were waiting for.
+//!
+//! Since wakers are thread-aware, notifying a task on the same thread simply
+//! moves it onto the run queue; tasks on other threads are woken via their
+//! executor.
+use std::{collections::VecDeque, task::Waker};
+
+use slab::Slab;
+
+struct Waiter<T> {
+    value: T,
+    waker: Option<Waker>,
+    notified: bool,
+}
+
+/// A FIFO list of waiters, each associated with a value of type `T`.
+pub(crate) struct WaitList<T> {
+    waiters: Slab<Waiter<T>>,
+    queue: VecDeque<usize>,
+}
+
+impl<T> WaitList<T> {
+    pub const fn new() -> Self {
+        Self {
+            waiters: Slab::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Add a waiter to the back of the list, returning a key which identifies
+    /// it.
+    pub fn push(&mut self, value: T, waker: &Waker) -> usize {
+        let key = self.waiters.insert(Waiter {
+            value,
+            waker: Some(waker.clone()),
+            notified: false,
+        });
+
+        self.queue.push_back(key);
+
+        key
+    }
+
+    /// Update the waker of a waiter that has not yet been notified.
+    pub fn register(&mut self, key: usize, waker: &Waker) {
+        let waiter = &mut self.waiters[key];
+
+        if !waiter.waker.as_ref().is_some_and(|w| w.will_wake(waker)) {
+            waiter.waker = Some(waker.clone());
+        }
+    }
+
+    pub fn is_notified(&self, key: usize) -> bool {
+        self.waiters[key].notified
+    }
+
+    /// Remove a waiter, returning its value and whether it had been
+    /// notified.
+    pub fn remove(&mut self, key: usize) -> (T, bool) {
+        let waiter = self.waiters.remove(key);
+
+        if !waiter.notified {
+            self.queue.retain(|k| *k != key);
+        }
+
+        (waiter.value, waiter.notified)
+    }
+
+    /// The value of the longest-waiting waiter that has not been notified.
+    pub fn front(&self) -> Option<&T> {
+        self.queue.front().map(|key| &self.waiters[*key].value)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.queue.front().map(|key| &mut self.waiters[*key].value)
+    }
+
+    /// Notify the longest-waiting waiter, returning `false` if there are no
+    /// waiters left to notify.
+    pub fn notify_front(&mut self) -> bool {
+        let Some(key) = self.queue.pop_front() else {
+            return false;
+        };
+
+        let waiter = &mut self.waiters[key];
+
+        waiter.notified = true;
+
+        if let Some(waker) = waiter.waker.take() {
+            waker.wake();
+        }
+
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Wake, Waker},
+    };
+
+    use super::WaitList;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn fifo_order() {
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut list = WaitList::new();
+
+        let a = list.push(1, &waker);
+        let b = list.push(2, &waker);
+        let c = list.push(3, &waker);
+
+        assert_eq!(list.front(), Some(&1));
+        assert!(list.notify_front());
+        assert!(list.is_notified(a));
+        assert!(!list.is_notified(b));
+
+        assert_eq!(list.remove(b), (2, false));
+        assert_eq!(list.front(), Some(&3));
+
+        assert!(list.notify_front());
+        assert!(list.is_notified(c));
+        assert!(list.is_empty());
+        assert!(!list.notify_front());
+
+        assert_eq!(list.remove(a), (1, true));
+        assert_eq!(list.remove(c), (3, true));
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+    }
+}
@@ -36,7 +36,9 @@ pub(crate) struct AsyncWriter<'a, T: AsFd + Unpin> {
     pub(crate) fd: T,
     pub(crate) io: ReactorIo,
     pub(crate) buf: &'a [u8],
-    pub(crate) seekable: bool,
+    /// The offset to access. `u64::MAX` uses and advances the file position,
+    /// and for non-seekable files the offset must be `0`.
+    pub(crate) offset: u64,
 }
 
 impl<T: AsFd + Unpin> Future for AsyncWriter<'_, T> {
@@ -53,7 +55,7 @@ impl<T: AsFd + Unpin> Future for AsyncWriter<'_, T> {
                         this.buf.as_ptr(),
                         this.buf.len() as _,
                     )
-                    .offset(if this.seekable { u64::MAX } else { 0 })
+                    .offset(this.offset)
                     .build(),
                     cx.waker().clone(),
                 )
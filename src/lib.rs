@@ -32,6 +32,14 @@
 //! });
 //! Executor::run();
 //! ```
+//!
+//! ## Cargo Features
+//!
+//! - `tracing`: Instrument the executor and reactor with the
+//!   [`tracing`](https://docs.rs/tracing) crate. Each task is given a span
+//!   covering its lifetime from spawn to completion, with an event for every
+//!   poll. Every SQE submitted to, and CQE reaped from, the ring emits an
+//!   event carrying the opcode, fd, result and latency of the operation.
 pub mod futures;
 pub(crate) mod reactor;
 pub mod task;
@@ -1,7 +1,9 @@
-use std::task::Waker;
+use std::{os::fd::RawFd, task::Waker};
 
 use uring::{MultishotUringIo, OneshotUringIo, ReactorUring};
 
+pub use uring::ReactorMetrics;
+
 mod uring;
 
 pub type ReactorIo = OneshotUringIo<Waker>;
@@ -22,6 +24,34 @@ impl Reactor {
         REACTOR.with(|r| r.new_multishot_io())
     }
 
+    pub fn metrics() -> ReactorMetrics {
+        REACTOR.with(|r| r.metrics())
+    }
+
+    pub fn is_supported(opcode: u8) -> bool {
+        REACTOR.with(|r| r.is_supported(opcode))
+    }
+
+    pub fn shutdown() {
+        REACTOR.with(|r| r.shutdown());
+    }
+
+    pub fn poll() {
+        REACTOR.with(|r| {
+            for waker in r.poll() {
+                waker.wake();
+            }
+        })
+    }
+
+    pub fn submit() {
+        REACTOR.with(|r| r.submit())
+    }
+
+    pub fn completion_fd() -> std::io::Result<RawFd> {
+        REACTOR.with(|r| r.completion_fd())
+    }
+
     pub fn react() {
         REACTOR.with(|r| {
             for waker in r.react() {
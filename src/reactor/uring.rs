@@ -1,15 +1,23 @@
 pub(crate) use io::{multishot::MultishotUringIo, oneshot::OneshotUringIo};
-use io_uring::{cqueue, squeue, CompletionQueue, IoUring};
+use io_uring::{cqueue, opcode, squeue, types::CancelBuilder, CompletionQueue, IoUring, Probe};
 use result::RingResults;
 use slab::Slab;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
 use std::{
     cell::{RefCell, RefMut},
+    collections::VecDeque,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
     rc::Rc,
 };
 
 mod io;
 mod result;
 
+/// The user data of `IORING_OP_ASYNC_CANCEL` requests, whose completions have
+/// no associated object.
+const CANCEL_USER_DATA: u64 = u64::MAX - 1;
+
 pub struct ReactorUring<T: Clone> {
     inner: Rc<RefCell<ReactorInner<T>>>,
 }
@@ -29,10 +37,91 @@ impl<T: Clone> ReactorUring<T> {
         MultishotUringIo::new(self.inner.clone())
     }
 
+    pub fn metrics(&self) -> ReactorMetrics {
+        self.inner.borrow().metrics
+    }
+
+    /// Returns `true` if the kernel supports the io_uring operation
+    /// `opcode`.
+    pub fn is_supported(&self, opcode: u8) -> bool {
+        self.inner.borrow().is_supported(opcode)
+    }
+
     pub fn react(&self) -> IoCompletionIter<'_, T> {
+        self.complete(1)
+    }
+
+    /// Submit any queued I/O and reap the completions that are available,
+    /// without waiting.
+    pub fn poll(&self) -> IoCompletionIter<'_, T> {
+        if let Some(fd) = &self.inner.borrow().completion_fd {
+            let mut buf = [0u8; std::mem::size_of::<u64>()];
+
+            // Reset the eventfd so that an external event loop only sees it
+            // as readable once new completions have been posted. A failure
+            // here means the counter was already zero.
+            unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr() as *mut _, buf.len()) };
+        }
+
+        self.complete(0)
+    }
+
+    pub fn submit(&self) {
+        self.inner.borrow().uring.submit().unwrap();
+    }
+
+    /// Return an eventfd which is signalled whenever a completion is posted to
+    /// the ring, creating and registering it on first use.
+    pub fn completion_fd(&self) -> std::io::Result<RawFd> {
+        let mut inner = self.inner.borrow_mut();
+
+        if let Some(fd) = &inner.completion_fd {
+            return Ok(fd.as_raw_fd());
+        }
+
+        let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        inner.uring.submitter().register_eventfd(fd.as_raw_fd())?;
+
+        Ok(inner.completion_fd.insert(fd).as_raw_fd())
+    }
+
+    /// Cancel all in-flight I/O and reap every outstanding completion,
+    /// returning the associated objects of the operations that were
+    /// outstanding.
+    pub fn shutdown(&self) -> Vec<T> {
+        let mut objs = Vec::new();
+
+        self.inner.borrow_mut().cancel_all();
+
+        while !self.inner.borrow().pending.is_empty() {
+            objs.extend(self.complete(1));
+        }
+
+        objs
+    }
+
+    fn complete(&self, want: usize) -> IoCompletionIter<'_, T> {
         let mut borrow = self.inner.borrow_mut();
 
-        borrow.uring.submit_and_wait(1).unwrap();
+        // Completions reaped while cancelling are already waiting to be
+        // handed out, so don't block for more.
+        let want = if borrow.deferred.is_empty() { want } else { 0 };
+
+        borrow.uring.submit_and_wait(want).unwrap();
+
+        let deferred = borrow.deferred.len() as u64;
+        let reaped = borrow.uring.completion().len() as u64 + deferred;
+        let metrics = &mut borrow.metrics;
+        metrics.reacts += 1;
+        metrics.cqes_reaped += reaped;
+        metrics.max_cqes_per_react = metrics.max_cqes_per_react.max(reaped);
 
         // SAFETY: This object lives along side both the `objs` and `results`
         // RefMuts. Therefore, `borrow` will remained borrowed for the lifetime
@@ -54,6 +143,27 @@ pub(crate) struct ReactorInner<T> {
     uring: IoUring,
     pending: Slab<PendingIo<T>>,
     results: RingResults,
+    metrics: ReactorMetrics,
+    completion_fd: Option<OwnedFd>,
+    /// The operations supported by the kernel, indexed by opcode.
+    supported: [bool; 256],
+    /// Whether `IORING_REGISTER_SYNC_CANCEL` is available, which requires
+    /// Linux 6.0.
+    sync_cancel: bool,
+    /// Completions reaped from the ring while waiting for a cancellation, to
+    /// be handed out by the next [IoCompletionIter].
+    deferred: VecDeque<cqueue::Entry>,
+}
+
+/// Counters maintained by the reactor, see [crate::task::Metrics].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReactorMetrics {
+    pub sqes_submitted: u64,
+    pub reacts: u64,
+    pub cqes_reaped: u64,
+    pub max_cqes_per_react: u64,
+    pub inflight_oneshot: usize,
+    pub inflight_multishot: usize,
 }
 
 #[derive(Clone, Copy)]
@@ -67,29 +177,110 @@ struct PendingIo<T> {
     assoc_obj: T,
     result_slab_idx: usize,
     kind: IoKind,
+    #[cfg(feature = "tracing")]
+    trace: SqeTrace,
+}
+
+/// Details of a submitted SQE, kept so that its completion can be correlated
+/// with the submission.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy)]
+struct SqeTrace {
+    opcode: u8,
+    fd: i32,
+    submitted_at: Instant,
+}
+
+/// Read the opcode and fd of an SQE.
+fn sqe_header(entry: &squeue::Entry) -> (u8, i32) {
+    // SAFETY: `squeue::Entry` is a `repr(C)` wrapper around the kernel's
+    // `io_uring_sqe`, which begins with a `u8` opcode, a `u8` flags field,
+    // a `u16` ioprio and then the `i32` fd.
+    unsafe {
+        let sqe = entry as *const squeue::Entry as *const u8;
+        (sqe.read(), sqe.add(4).cast::<i32>().read())
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl SqeTrace {
+    fn new(entry: &squeue::Entry) -> Self {
+        let (opcode, fd) = sqe_header(entry);
+
+        Self {
+            opcode,
+            fd,
+            submitted_at: Instant::now(),
+        }
+    }
 }
 
 impl<T> ReactorInner<T> {
     fn new() -> Self {
+        let uring = IoUring::new(1024).unwrap();
+        let mut probe = Probe::new();
+
+        // Kernels older than 5.6 can't be probed; assume that everything is
+        // supported and let the kernel reject what it doesn't know.
+        let supported = match uring.submitter().register_probe(&mut probe) {
+            Ok(()) => std::array::from_fn(|op| probe.is_supported(op as u8)),
+            Err(_) => [true; 256],
+        };
+
         Self {
-            uring: IoUring::new(1024).unwrap(),
+            uring,
             pending: Slab::new(),
             results: RingResults::new(),
+            metrics: ReactorMetrics::default(),
+            completion_fd: None,
+            supported,
+            sync_cancel: true,
+            deferred: VecDeque::new(),
         }
     }
 
+    fn is_supported(&self, opcode: u8) -> bool {
+        self.supported[opcode as usize]
+    }
+
     fn submit_io(&mut self, entry: squeue::Entry, obj: T, kind: IoKind) -> (u64, usize) {
+        if !self.is_supported(sqe_header(&entry).0) {
+            return self.complete_unsupported(kind);
+        }
+
         let result_slab_idx = match kind {
-            IoKind::Oneshot => self.results.get_oneshot().create_slot(),
-            IoKind::Multi => self.results.get_multishot().create_slot(),
+            IoKind::Oneshot => {
+                self.metrics.inflight_oneshot += 1;
+                self.results.get_oneshot().create_slot()
+            }
+            IoKind::Multi => {
+                self.metrics.inflight_multishot += 1;
+                self.results.get_multishot().create_slot()
+            }
         };
 
+        self.metrics.sqes_submitted += 1;
+
+        #[cfg(feature = "tracing")]
+        let trace = SqeTrace::new(&entry);
+
         let slot = self.pending.insert(PendingIo {
             assoc_obj: obj,
             result_slab_idx,
             kind,
+            #[cfg(feature = "tracing")]
+            trace,
         });
 
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            user_data = slot,
+            opcode = trace.opcode,
+            fd = trace.fd,
+            multishot = matches!(kind, IoKind::Multi),
+            "sqe submitted"
+        );
+
         unsafe {
             self.uring
                 .submission()
@@ -99,6 +290,112 @@ impl<T> ReactorInner<T> {
 
         (slot as u64, result_slab_idx)
     }
+
+    /// Complete an operation that the kernel doesn't support without
+    /// submitting it, so that the caller sees `EOPNOTSUPP` rather than the
+    /// kernel's generic `EINVAL`.
+    fn complete_unsupported(&mut self, kind: IoKind) -> (u64, usize) {
+        let result_slab_idx = match kind {
+            IoKind::Oneshot => {
+                let results = self.results.get_oneshot();
+                let idx = results.create_slot();
+                results.set_result(-libc::EOPNOTSUPP, idx);
+                idx
+            }
+            IoKind::Multi => {
+                let results = self.results.get_multishot();
+                let idx = results.create_slot();
+                results.push_result(-libc::EOPNOTSUPP, idx);
+                results.set_finished(idx);
+                idx
+            }
+        };
+
+        // There's nothing in flight, so the user data is never used.
+        (u64::MAX, result_slab_idx)
+    }
+
+    /// Synchronously cancel the in-flight operation identified by
+    /// `user_data`. Once this function returns the kernel is no longer
+    /// processing the operation and its completion has been posted.
+    fn cancel(&mut self, user_data: u64) {
+        if !self.sync_cancel(CancelBuilder::user_data(user_data)) {
+            self.async_cancel(vec![user_data]);
+        }
+    }
+
+    fn cancel_all(&mut self) {
+        if !self.sync_cancel(CancelBuilder::any()) {
+            let targets = self.pending.iter().map(|(slot, _)| slot as u64).collect();
+            self.async_cancel(targets);
+        }
+    }
+
+    /// Cancel the operations matched by `builder` with
+    /// `IORING_REGISTER_SYNC_CANCEL`, returning `false` if the kernel doesn't
+    /// support it.
+    fn sync_cancel(&mut self, builder: CancelBuilder) -> bool {
+        // Only operations that have been submitted to the kernel can be
+        // cancelled, flush anything that is still sitting in the SQ.
+        self.uring
+            .submit()
+            .expect("Should be able to submit pending IO");
+
+        if !self.sync_cancel {
+            return false;
+        }
+
+        match self.uring.submitter().register_sync_cancel(None, builder) {
+            Ok(()) => true,
+            // The operation has already completed.
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => true,
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                self.sync_cancel = false;
+                false
+            }
+            Err(e) => panic!("Should be able to cancel in-flight IO: {e}"),
+        }
+    }
+
+    /// Cancel each of `targets` with `IORING_OP_ASYNC_CANCEL` and wait until
+    /// their final completions have been posted. Completions reaped in the
+    /// meantime are kept in `deferred`.
+    fn async_cancel(&mut self, mut targets: Vec<u64>) {
+        let finished = |entry: &cqueue::Entry| !cqueue::more(entry.flags());
+
+        targets.retain(|t| {
+            !self
+                .deferred
+                .iter()
+                .any(|e| e.user_data() == *t && finished(e))
+        });
+
+        for &target in &targets {
+            let entry = opcode::AsyncCancel::new(target)
+                .build()
+                .user_data(CANCEL_USER_DATA);
+
+            while unsafe { self.uring.submission().push(&entry) }.is_err() {
+                self.uring
+                    .submit()
+                    .expect("Should be able to submit pending IO");
+            }
+        }
+
+        while !targets.is_empty() {
+            self.uring
+                .submit_and_wait(1)
+                .expect("Should be able to wait for cancelled IO");
+
+            for entry in self.uring.completion() {
+                if finished(&entry) {
+                    targets.retain(|t| *t != entry.user_data());
+                }
+
+                self.deferred.push_back(entry);
+            }
+        }
+    }
 }
 
 pub struct IoCompletionIter<'a, T: Clone> {
@@ -110,7 +407,17 @@ impl<T: Clone> Iterator for IoCompletionIter<'_, T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let entry = self.compl_queue.next()?;
+        let entry = loop {
+            let entry = match self.ring.deferred.pop_front() {
+                Some(entry) => entry,
+                None => self.compl_queue.next()?,
+            };
+
+            // Cancellation requests have no associated object.
+            if entry.user_data() != CANCEL_USER_DATA {
+                break entry;
+            }
+        };
 
         let pending_io = self
             .ring
@@ -119,6 +426,17 @@ impl<T: Clone> Iterator for IoCompletionIter<'_, T> {
             .unwrap()
             .clone();
 
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            user_data = entry.user_data(),
+            opcode = pending_io.trace.opcode,
+            fd = pending_io.trace.fd,
+            result = entry.result(),
+            more = cqueue::more(entry.flags()),
+            latency_us = pending_io.trace.submitted_at.elapsed().as_micros() as u64,
+            "cqe completed"
+        );
+
         match pending_io.kind {
             IoKind::Oneshot => {
                 self.ring
@@ -126,12 +444,15 @@ impl<T: Clone> Iterator for IoCompletionIter<'_, T> {
                     .get_oneshot()
                     .set_result(entry.result(), pending_io.result_slab_idx);
                 self.ring.pending.remove(entry.user_data() as usize);
+                self.ring.metrics.inflight_oneshot -= 1;
             }
             IoKind::Multi => {
                 let results = self.ring.results.get_multishot();
                 results.push_result(entry.result(), pending_io.result_slab_idx);
                 if !cqueue::more(entry.flags()) {
                     results.set_finished(pending_io.result_slab_idx);
+                    self.ring.pending.remove(entry.user_data() as usize);
+                    self.ring.metrics.inflight_multishot -= 1;
                 }
             }
         }
@@ -150,7 +471,7 @@ mod tests {
     use io_uring::{opcode, types};
     use libc::{AF_LOCAL, SOCK_NONBLOCK, SOCK_STREAM};
 
-    use super::ReactorUring;
+    use super::{IoKind, ReactorUring};
 
     fn write(fd: impl AsFd, buf: &[u8]) {
         let ret = unsafe {
@@ -167,6 +488,18 @@ mod tests {
     }
 
     fn read(fd: impl AsFd, buf: &mut [u8]) {
+        // The socket is non-blocking and the peer's write may not have been
+        // submitted yet, so wait for it to become readable.
+        let mut pfd = libc::pollfd {
+            fd: fd.as_fd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        if unsafe { libc::poll(&mut pfd, 1, -1) } == -1 {
+            panic!("poll failed");
+        }
+
         let ret = unsafe {
             libc::read(
                 fd.as_fd().as_raw_fd(),
@@ -176,7 +509,7 @@ mod tests {
         };
 
         if ret == -1 {
-            panic!("write failed");
+            panic!("read failed");
         }
     }
 
@@ -264,6 +597,99 @@ mod tests {
         });
     }
 
+    #[test]
+    fn shutdown_cancels_inflight_io() {
+        run_test(|a, _b, uring| {
+            let mut buf = [0];
+
+            let mut io = uring.new_oneshot_io();
+            assert!(matches!(
+                io.submit_or_get_result(|| {
+                    (
+                        opcode::Read::new(types::Fd(a.as_raw_fd()), buf.as_mut_ptr(), 1).build(),
+                        10,
+                    )
+                }),
+                Poll::Pending
+            ));
+
+            assert_eq!(uring.shutdown(), vec![10]);
+            assert!(uring.inner.borrow().pending.is_empty());
+
+            assert!(matches!(
+                io.submit_or_get_result(|| panic!("Should not be called")),
+                Poll::Ready(Err(e)) if e.raw_os_error() == Some(libc::ECANCELED)
+            ));
+        });
+    }
+
+    #[test]
+    fn async_cancel_fallback() {
+        run_test(|a, _b, uring| {
+            let mut buf = [0];
+
+            // Pretend that the kernel predates synchronous cancellation.
+            uring.inner.borrow_mut().sync_cancel = false;
+
+            let mut io1 = uring.new_oneshot_io();
+            let mut io2 = uring.new_oneshot_io();
+
+            for (io, obj) in [(&mut io1, 10), (&mut io2, 20)] {
+                assert!(matches!(
+                    io.submit_or_get_result(|| {
+                        (
+                            opcode::Read::new(types::Fd(a.as_raw_fd()), buf.as_mut_ptr(), 1)
+                                .build(),
+                            obj,
+                        )
+                    }),
+                    Poll::Pending
+                ));
+            }
+
+            drop(io1);
+
+            // The cancelled read has completed, without blocking.
+            assert_eq!(uring.poll().collect::<Vec<_>>(), vec![10]);
+
+            assert_eq!(uring.shutdown(), vec![20]);
+            assert!(uring.inner.borrow().pending.is_empty());
+
+            assert!(matches!(
+                io2.submit_or_get_result(|| panic!("Should not be called")),
+                Poll::Ready(Err(e)) if e.raw_os_error() == Some(libc::ECANCELED)
+            ));
+        });
+    }
+
+    #[test]
+    fn poll_does_not_block() {
+        run_test(|a, b, uring| {
+            let mut buf = [0];
+
+            let mut io = uring.new_oneshot_io();
+            assert!(matches!(
+                io.submit_or_get_result(|| {
+                    (
+                        opcode::Read::new(types::Fd(a.as_raw_fd()), buf.as_mut_ptr(), 1).build(),
+                        10,
+                    )
+                }),
+                Poll::Pending
+            ));
+
+            assert_eq!(uring.poll().next(), None);
+
+            write(b, &[2]);
+
+            assert_eq!(uring.react().collect::<Vec<_>>(), vec![10]);
+            assert!(matches!(
+                io.submit_or_get_result(|| panic!("Should not be called")),
+                Poll::Ready(Ok(1))
+            ));
+        });
+    }
+
     #[test]
     fn single_wakeup_write() {
         run_test(|a, b, uring| {
@@ -405,4 +831,52 @@ mod tests {
             t1.join().unwrap();
         });
     }
+
+    #[test]
+    fn multishot_finished_cleanup() {
+        run_test(|a, _b, uring| {
+            // The socket isn't listening, so the accept fails straight away
+            // and no further completions are posted for it.
+            let (_, slot) = uring.inner.borrow_mut().submit_io(
+                opcode::AcceptMulti::new(types::Fd(a.as_raw_fd())).build(),
+                10,
+                IoKind::Multi,
+            );
+
+            assert_eq!(uring.react().collect::<Vec<_>>(), [10]);
+            assert!(uring.inner.borrow().pending.is_empty());
+
+            uring
+                .inner
+                .borrow_mut()
+                .results
+                .get_multishot()
+                .drop_result(slot);
+        });
+    }
+
+    #[test]
+    fn unsupported_opcode() {
+        run_test(|a, _b, uring| {
+            let buf = [0];
+
+            uring.inner.borrow_mut().supported[opcode::Write::CODE as usize] = false;
+            assert!(!uring.is_supported(opcode::Write::CODE));
+
+            let mut io = uring.new_oneshot_io();
+            let result = io.submit_or_get_result(|| {
+                (
+                    opcode::Write::new(types::Fd(a.as_raw_fd()), buf.as_ptr(), buf.len() as _)
+                        .build(),
+                    10,
+                )
+            });
+
+            assert!(matches!(
+                result,
+                Poll::Ready(Err(e)) if e.kind() == std::io::ErrorKind::Unsupported
+            ));
+            assert_eq!(uring.metrics().sqes_submitted, 0);
+        });
+    }
 }
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc, task::Poll};
 
-use io_uring::{squeue, types::CancelBuilder};
+use io_uring::squeue;
 
 use crate::reactor::uring::{result::MultishotResult, IoKind, ReactorInner};
 
@@ -29,24 +29,24 @@ impl<T> MultishotUringIo<T> {
         &mut self,
         f: impl FnOnce() -> (squeue::Entry, T),
     ) -> Poll<Option<std::io::Result<i32>>> {
-        match self.state {
+        let slot = match self.state {
             IoState::New => {
                 let (entry, obj) = f();
                 let (user_data, result_slot) =
                     self.ring.borrow_mut().submit_io(entry, obj, IoKind::Multi);
                 self.state = IoState::Submitted(result_slot, user_data);
-                Poll::Pending
+                result_slot
             }
-            IoState::Submitted(slot, _) => {
-                let mut ring = self.ring.borrow_mut();
-                let result_store = ring.results.get_multishot();
+            IoState::Submitted(slot, _) => slot,
+        };
 
-                match result_store.pop_result(slot) {
-                    MultishotResult::Value(v) => Poll::Ready(Some(reactor_value_to_result(v))),
-                    MultishotResult::Pending => Poll::Pending,
-                    MultishotResult::Finished => Poll::Ready(None),
-                }
-            }
+        let mut ring = self.ring.borrow_mut();
+        let result_store = ring.results.get_multishot();
+
+        match result_store.pop_result(slot) {
+            MultishotResult::Value(v) => Poll::Ready(Some(reactor_value_to_result(v))),
+            MultishotResult::Pending => Poll::Pending,
+            MultishotResult::Finished => Poll::Ready(None),
         }
     }
 }
@@ -56,12 +56,9 @@ impl<T> Drop for MultishotUringIo<T> {
         if let IoState::Submitted(slot, user_data) = self.state {
             let mut ring = self.ring.borrow_mut();
 
-            ring.uring
-                .submitter()
-                .register_sync_cancel(None, CancelBuilder::user_data(user_data))
-                .expect("Should be able to cancel in-flight multishot IO");
-
-            ring.results.get_multishot().drop_result(slot);
+            if ring.results.get_multishot().drop_result(slot) {
+                ring.cancel(user_data);
+            }
         }
     }
 }
@@ -9,7 +9,7 @@ use super::reactor_value_to_result;
 #[derive(Debug)]
 enum IoState {
     New,
-    Submitted(usize),
+    Submitted(usize, u64),
     Finished(i32),
 }
 
@@ -22,7 +22,7 @@ impl From<&IoState> for Poll<std::io::Result<i32>> {
     fn from(value: &IoState) -> Self {
         match value {
             IoState::New => Poll::Pending,
-            IoState::Submitted(_) => Poll::Pending,
+            IoState::Submitted(..) => Poll::Pending,
             IoState::Finished(result) => Poll::Ready(reactor_value_to_result(*result)),
         }
     }
@@ -40,24 +40,24 @@ impl<T> OneshotUringIo<T> {
         &mut self,
         f: impl FnOnce() -> (squeue::Entry, T),
     ) -> Poll<std::io::Result<i32>> {
-        match self.state {
-            IoState::New => {
-                let (entry, obj) = f();
-                let (_, result_slot) =
-                    self.ring
-                        .borrow_mut()
-                        .submit_io(entry, obj, IoKind::Oneshot);
-                self.state = IoState::Submitted(result_slot);
-            }
-            IoState::Submitted(slot) => {
-                let mut ring = self.ring.borrow_mut();
-                let result_store = ring.results.get_oneshot();
+        if let IoState::New = self.state {
+            let (entry, obj) = f();
+            let (user_data, result_slot) =
+                self.ring
+                    .borrow_mut()
+                    .submit_io(entry, obj, IoKind::Oneshot);
+            self.state = IoState::Submitted(result_slot, user_data);
+        }
 
-                if let Some(res) = result_store.get_result(slot) {
-                    self.state = IoState::Finished(res);
-                }
+        // Check for a result straight after submission too, as the reactor
+        // completes unsupported operations immediately.
+        if let IoState::Submitted(slot, _) = self.state {
+            let mut ring = self.ring.borrow_mut();
+            let result_store = ring.results.get_oneshot();
+
+            if let Some(res) = result_store.get_result(slot) {
+                self.state = IoState::Finished(res);
             }
-            IoState::Finished(_) => {}
         }
 
         (&self.state).into()
@@ -66,10 +66,14 @@ impl<T> OneshotUringIo<T> {
 
 impl<T> Drop for OneshotUringIo<T> {
     fn drop(&mut self) {
-        if let IoState::Submitted(slot) = self.state {
+        if let IoState::Submitted(slot, user_data) = self.state {
             let mut ring = self.ring.borrow_mut();
 
-            ring.results.get_oneshot().drop_result(slot);
+            // The kernel may still be using buffers owned by the future that
+            // is being dropped, make sure the operation is no longer running.
+            if ring.results.get_oneshot().drop_result(slot) {
+                ring.cancel(user_data);
+            }
         }
     }
 }
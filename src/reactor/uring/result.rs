@@ -43,13 +43,17 @@ impl OneshotStore {
         res
     }
 
-    pub fn drop_result(&mut self, idx: usize) {
+    /// Drop the result in slot `idx`, returning `true` if the operation is
+    /// still in-flight.
+    pub fn drop_result(&mut self, idx: usize) -> bool {
         let r_entry = self.0.get_mut(idx).unwrap();
 
         if matches!(r_entry, ResultState::Set(_)) {
             self.0.remove(idx);
+            false
         } else {
             *r_entry = ResultState::Dropped;
+            true
         }
     }
 
@@ -101,11 +105,15 @@ impl MultishotStore {
         }
     }
 
-    pub fn drop_result(&mut self, idx: usize) {
+    /// Drop the results in slot `idx`, returning `true` if the operation is
+    /// still in-flight.
+    pub fn drop_result(&mut self, idx: usize) -> bool {
         if self.0.get_mut(idx).unwrap().finished {
             self.0.remove(idx);
+            false
         } else {
             self.0.get_mut(idx).unwrap().dropped = true;
+            true
         }
     }
 
@@ -91,17 +91,45 @@
 //! Executor::run();
 //! assert_eq!(*cell.lock().unwrap(), 3);
 //! ```
+//!
+//! A task's [Waker] may be invoked from any thread. When a task is woken from
+//! a thread other than the one it was spawned on, the wakeup is forwarded to
+//! the owning thread's executor which will poll the task once it next runs.
+//!
+//! # Priorities
+//!
+//! Tasks can be given a [Priority] by spawning them through a [Builder].
+//! Whenever more than one task is ready to run, higher-priority tasks are
+//! polled first. To prevent a busy high-priority task from starving the rest
+//! of the thread, tasks that have been passed over for too long are aged and
+//! polled regardless of their priority.
+//!
+//! ```
+//! use trale::task::{Builder, Executor, Priority};
+//! Builder::new()
+//!     .priority(Priority::Low)
+//!     .spawn(async { println!("Bulk transfer"); });
+//! Builder::new()
+//!     .priority(Priority::High)
+//!     .spawn(async { println!("Control plane"); });
+//! Executor::run();
+//! ```
 use std::{
     cell::RefCell,
+    collections::VecDeque,
     future::Future,
     mem::transmute,
+    os::fd::RawFd,
     pin::Pin,
+    rc::Rc,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{sync_channel, Receiver},
         Arc,
     },
     task::{ready, Context, Poll, Wake, Waker},
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
 };
 
 use slab::Slab;
@@ -111,22 +139,187 @@ use crate::{
     reactor::Reactor,
 };
 
-struct TaskId(AtomicUsize);
+/// The number of times a queued task can be passed over in favour of
+/// higher-priority tasks before it is polled regardless of its priority.
+const AGING_THRESHOLD: u64 = 32;
 
-impl Wake for TaskId {
-    fn wake(self: Arc<TaskId>) {
+struct TaskId {
+    slot: AtomicUsize,
+    notified: AtomicBool,
+    remote: Arc<RemoteWakes>,
+}
+
+impl TaskId {
+    fn wake_local(self: Arc<TaskId>) {
         EXEC.with(|exec| {
             let mut exec = exec.borrow_mut();
-            if let Some(task) = exec.waiting.try_remove(self.0.load(Ordering::Relaxed)) {
-                exec.run_q.push(task);
+            let slot = self.slot.load(Ordering::Relaxed);
+
+            exec.metrics.wakeups += 1;
+
+            match exec.waiting.get(slot) {
+                Some(task) if Arc::ptr_eq(&task.id, &self) => {
+                    let task = exec.waiting.remove(slot);
+                    exec.push(task);
+                }
+                // The task is either queued or currently being polled; make
+                // sure it is polled again rather than put to sleep.
+                _ => self.notified.store(true, Ordering::Relaxed),
             }
         });
     }
 }
 
+impl Wake for TaskId {
+    fn wake(self: Arc<TaskId>) {
+        if thread::current().id() == self.remote.owner {
+            self.wake_local();
+        } else {
+            self.remote.clone().push(self);
+        }
+    }
+}
+
+/// Wakeups for an executor's tasks which originate from other threads.
+///
+/// Since each thread has its own executor, a waker that is invoked from a
+/// foreign thread can't touch the run queue directly. Instead, the task is
+/// queued here and the owning executor is notified via an [Event], which it
+/// always has a pending read on whilst it is sleeping in the reactor.
+struct RemoteWakes {
+    owner: ThreadId,
+    queue: std::sync::Mutex<Vec<Arc<TaskId>>>,
+    evt: Event,
+}
+
+impl RemoteWakes {
+    fn new() -> Self {
+        Self {
+            owner: thread::current().id(),
+            queue: std::sync::Mutex::new(Vec::new()),
+            evt: Event::new().unwrap(),
+        }
+    }
+
+    fn push(&self, id: Arc<TaskId>) {
+        let mut queue = self.queue.lock().unwrap();
+
+        queue.push(id);
+
+        // Only the first wakeup of a batch needs to notify the executor; it
+        // drains the entire queue when it handles the event.
+        if queue.len() == 1 {
+            self.evt.notify_one().unwrap();
+        }
+    }
+}
+
+/// Invoked by the reactor, on the owning thread, once the event has been
+/// signalled.
+impl Wake for RemoteWakes {
+    fn wake(self: Arc<Self>) {
+        let woken = std::mem::take(&mut *self.queue.lock().unwrap());
+
+        for id in woken {
+            id.wake_local();
+        }
+    }
+}
+
+/// The executor's pending read on [RemoteWakes::evt].
+struct RemoteListener {
+    waiter: Option<Pin<Box<EventWaiter<'static>>>>,
+    evt: Event,
+}
+
+/// The scheduling priority of a task.
+///
+/// When multiple tasks are ready to run, those with a higher priority are
+/// polled first. See the [module-level documentation](self) for more
+/// information.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Background work, such as bulk transfers.
+    Low,
+    /// The priority of tasks spawned via [Executor::spawn].
+    #[default]
+    Normal,
+    /// Latency-sensitive work.
+    High,
+}
+
+impl Priority {
+    const LEVELS: usize = 3;
+
+    fn level(self) -> usize {
+        self as usize
+    }
+}
+
 struct Task {
     id: Arc<TaskId>,
+    priority: Priority,
     future: Pin<Box<dyn Future<Output = ()>>>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+struct QueuedTask {
+    task: Task,
+    enqueued_at: u64,
+}
+
+/// A multi-level run queue.
+///
+/// Each priority level has its own FIFO queue. Tasks are popped from the
+/// highest non-empty level unless the head of a lower level has been waiting
+/// for more than [AGING_THRESHOLD] pops, in which case the longest-waiting
+/// starved task is popped instead.
+struct RunQueue {
+    levels: [VecDeque<QueuedTask>; Priority::LEVELS],
+    tick: u64,
+}
+
+impl RunQueue {
+    const fn new() -> Self {
+        Self {
+            levels: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            tick: 0,
+        }
+    }
+
+    fn push(&mut self, task: Task) {
+        self.levels[task.priority.level()].push_back(QueuedTask {
+            task,
+            enqueued_at: self.tick,
+        });
+    }
+
+    fn pop(&mut self) -> Option<Task> {
+        self.tick += 1;
+
+        let top = self.levels.iter().rposition(|q| !q.is_empty())?;
+
+        let starved = self.levels[..top]
+            .iter()
+            .enumerate()
+            .filter_map(|(level, q)| q.front().map(|t| (level, t.enqueued_at)))
+            .filter(|(_, enqueued_at)| self.tick - enqueued_at > AGING_THRESHOLD)
+            .min_by_key(|(_, enqueued_at)| *enqueued_at)
+            .map(|(level, _)| level);
+
+        self.levels[starved.unwrap_or(top)]
+            .pop_front()
+            .map(|t| t.task)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.levels.iter().all(|q| q.is_empty())
+    }
+
+    fn len(&self) -> usize {
+        self.levels.iter().map(|q| q.len()).sum()
+    }
 }
 
 /// The async executor.
@@ -136,18 +329,83 @@ struct Task {
 /// [Executor::block_on] function.
 pub struct Executor {
     waiting: Slab<Task>,
-    run_q: Vec<Task>,
+    run_q: RunQueue,
+    metrics: Metrics,
+    remote: Option<Arc<RemoteWakes>>,
+    remote_listener: Option<RemoteListener>,
 }
 
 thread_local! {
     static EXEC: RefCell<Executor> = const { RefCell::new(
         Executor {
             waiting: Slab::new(),
-            run_q: Vec::new(),
+            run_q: RunQueue::new(),
+            metrics: Metrics::new(),
+            remote: None,
+            remote_listener: None,
         }
     )}
 }
 
+/// A snapshot of an executor's runtime counters.
+///
+/// Obtained via [Executor::metrics]. All counters are cumulative since the
+/// executor for the current thread was first used, with the exception of the
+/// in-flight operation counts which reflect the state of the reactor at the
+/// time the snapshot was taken.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of tasks that have been spawned.
+    pub tasks_spawned: u64,
+    /// The number of tasks that have run to completion.
+    pub tasks_completed: u64,
+    /// The number of times a task's future has been polled.
+    pub polls: u64,
+    /// The number of times a task's waker has been invoked.
+    pub wakeups: u64,
+    /// The total time spent polling tasks.
+    pub poll_time: Duration,
+    /// The total time spent in the reactor, including waiting for I/O.
+    pub react_time: Duration,
+    /// The largest number of tasks that have been ready to run at once.
+    pub max_run_queue_depth: usize,
+    /// The number of submission queue entries pushed to the ring.
+    pub sqes_submitted: u64,
+    /// The number of times the reactor has waited for completions.
+    pub reacts: u64,
+    /// The number of completion queue entries reaped by the reactor. Divide
+    /// by [Metrics::reacts] to obtain the average number reaped per wait.
+    pub cqes_reaped: u64,
+    /// The largest number of completion queue entries reaped by a single
+    /// wait.
+    pub max_cqes_per_react: u64,
+    /// The number of oneshot operations that have been submitted to the ring
+    /// but have not yet completed.
+    pub inflight_oneshot: usize,
+    /// The number of multishot operations that are still armed in the ring.
+    pub inflight_multishot: usize,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            tasks_spawned: 0,
+            tasks_completed: 0,
+            polls: 0,
+            wakeups: 0,
+            poll_time: Duration::ZERO,
+            react_time: Duration::ZERO,
+            max_run_queue_depth: 0,
+            sqes_submitted: 0,
+            reacts: 0,
+            cqes_reaped: 0,
+            max_cqes_per_react: 0,
+            inflight_oneshot: 0,
+            inflight_multishot: 0,
+        }
+    }
+}
+
 /// A handle to a running task.
 ///
 /// You can call [TaskJoiner::join] from a synchronous context to block
@@ -157,8 +415,10 @@ thread_local! {
 /// the return value is lost, aka detatch-on-drop.
 pub struct TaskJoiner<'a, T> {
     rx: Receiver<T>,
-    _evt: Event,
+    /// Declared before `_evt` so that it is dropped first; the waiter borrows
+    /// the event.
     finished: EventWaiter<'a>,
+    _evt: Event,
 }
 
 impl<'a, T> TaskJoiner<'a, T> {
@@ -182,15 +442,42 @@ impl<'a, T> Future for TaskJoiner<'a, T> {
     }
 }
 
-impl Executor {
-    /// Spawn a new future and add it to this thread's run queue. If called from
-    /// an already-running asynchronous task, the future will be queued for
-    /// execution. If called from a synchronous context, the task will *not* be
-    /// executed until [Executor::run] is called.
-    ///
-    /// A [TaskJoiner] is returned which can be used to wait for completion of
-    /// the future `f` and obtain it's return value.
-    pub fn spawn<'a, Fut, T>(f: Fut) -> TaskJoiner<'a, T>
+/// Task factory.
+///
+/// A builder which can be used to configure the properties of a new task
+/// before spawning it. [Executor::spawn] is equivalent to spawning with a
+/// default-constructed builder.
+///
+/// # Example
+///
+/// ```
+/// use trale::task::{Builder, Executor, Priority};
+/// let task = Builder::new()
+///     .priority(Priority::High)
+///     .spawn(async { 2 + 8 });
+/// Executor::run();
+/// assert_eq!(task.join(), 10);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    priority: Priority,
+}
+
+impl Builder {
+    /// Create a new builder with the default task configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [Priority] of the task.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Spawn a new future with this configuration and add it to this thread's
+    /// run queue. See [Executor::spawn] for more information.
+    pub fn spawn<'a, Fut, T>(self, f: Fut) -> TaskJoiner<'a, T>
     where
         Fut: Future<Output = T> + 'static,
         T: Send + 'static,
@@ -205,26 +492,121 @@ impl Executor {
             let _ = tx.send(value);
         };
 
-        let task = Task {
-            id: Arc::new(TaskId(AtomicUsize::new(0))),
-            future: Box::pin(fut),
-        };
-
-        EXEC.with(|exec| {
-            exec.borrow_mut().run_q.push(task);
-        });
+        Executor::spawn_task(self.priority, fut);
 
-        // SAFETY: This is safe since the borrowed FD is in the same structure
-        // that contains the waiter, therefore waiter can never outlive the
-        // event.
+        // SAFETY: The event is stored in the same structure as the waiter and
+        // is declared after it, so it is dropped after the waiter. The waiter
+        // therefore never outlives the event.
         let waiter: EventWaiter<'static> = unsafe { transmute(evt.wait()) };
 
         TaskJoiner {
             rx,
-            _evt: evt,
             finished: waiter,
+            _evt: evt,
         }
     }
+}
+
+impl Executor {
+    fn push(&mut self, task: Task) {
+        self.run_q.push(task);
+        self.metrics.max_run_queue_depth = self.metrics.max_run_queue_depth.max(self.run_q.len());
+    }
+
+    /// Ensure that the executor will be woken from the reactor when a task is
+    /// woken from another thread.
+    fn arm_remote_wakes() {
+        loop {
+            let consumed = EXEC.with(|exec| {
+                let exec = &mut *exec.borrow_mut();
+
+                let remote = exec.remote.as_ref()?;
+
+                let listener = exec.remote_listener.get_or_insert_with(|| RemoteListener {
+                    waiter: None,
+                    evt: remote.evt.clone(),
+                });
+
+                let waiter = listener.waiter.get_or_insert_with(|| {
+                    // SAFETY: The waiter is always dropped before the event, as
+                    // both live in the listener and the waiter is declared
+                    // first.
+                    Box::pin(unsafe {
+                        transmute::<EventWaiter<'_>, EventWaiter<'static>>(listener.evt.wait())
+                    })
+                });
+
+                let waker = Waker::from(remote.clone());
+
+                match waiter.as_mut().poll(&mut Context::from_waker(&waker)) {
+                    Poll::Pending => None,
+                    Poll::Ready(_) => {
+                        listener.waiter = None;
+                        Some(remote.clone())
+                    }
+                }
+            });
+
+            // The previous read completed; drain the queue and re-arm.
+            match consumed {
+                Some(remote) => remote.wake(),
+                None => return,
+            }
+        }
+    }
+
+    fn spawn_task(priority: Priority, fut: impl Future<Output = ()> + 'static) {
+        EXEC.with(|exec| {
+            let mut exec = exec.borrow_mut();
+            exec.metrics.tasks_spawned += 1;
+
+            let remote = exec
+                .remote
+                .get_or_insert_with(|| Arc::new(RemoteWakes::new()))
+                .clone();
+
+            #[cfg(feature = "tracing")]
+            let span = {
+                let span = tracing::debug_span!(
+                    "task",
+                    id = exec.metrics.tasks_spawned,
+                    priority = ?priority
+                );
+                tracing::debug!(parent: &span, "spawned");
+                span
+            };
+
+            exec.push(Task {
+                id: Arc::new(TaskId {
+                    slot: AtomicUsize::new(0),
+                    notified: AtomicBool::new(false),
+                    remote,
+                }),
+                priority,
+                future: Box::pin(fut),
+                #[cfg(feature = "tracing")]
+                span,
+            });
+        });
+    }
+
+    /// Spawn a new future and add it to this thread's run queue. If called from
+    /// an already-running asynchronous task, the future will be queued for
+    /// execution. If called from a synchronous context, the task will *not* be
+    /// executed until [Executor::run] is called.
+    ///
+    /// The task is spawned with [Priority::Normal]; use a [Builder] to spawn a
+    /// task with a different priority.
+    ///
+    /// A [TaskJoiner] is returned which can be used to wait for completion of
+    /// the future `f` and obtain it's return value.
+    pub fn spawn<'a, Fut, T>(f: Fut) -> TaskJoiner<'a, T>
+    where
+        Fut: Future<Output = T> + 'static,
+        T: Send + 'static,
+    {
+        Builder::new().spawn(f)
+    }
 
     /// A convenience function for waiting on a future from a synchronous
     /// context. This is the equivalent of calling:
@@ -245,11 +627,89 @@ impl Executor {
     {
         let joiner = Self::spawn(f);
 
-        Self::executor_loop();
+        Self::executor_loop(|| false);
 
         joiner.join()
     }
 
+    /// Run the executor for this thread until `f` completes.
+    ///
+    /// Unlike [Executor::block_on], this function returns as soon as `f` has
+    /// finished, even if other tasks on this thread have not. Those tasks are
+    /// left queued and will continue to make progress the next time the
+    /// executor is run, or can be cancelled with [Executor::shutdown].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trale::futures::timer::Timer;
+    /// use trale::task::Executor;
+    /// use std::time::Duration;
+    /// Executor::spawn(async {
+    ///     loop {
+    ///         Timer::sleep(Duration::from_millis(10)).unwrap().await;
+    ///     }
+    /// });
+    /// let x = Executor::run_until(async {
+    ///     Timer::sleep(Duration::from_millis(50)).unwrap().await;
+    ///     2 + 8
+    /// });
+    /// assert_eq!(x, 10);
+    /// Executor::shutdown();
+    /// ```
+    pub fn run_until<Fut, T>(f: Fut) -> T
+    where
+        Fut: Future<Output = T> + 'static,
+        T: 'static,
+    {
+        let result = Rc::new(RefCell::new(None));
+
+        {
+            let result = result.clone();
+            Self::spawn_task(Priority::Normal, async move {
+                *result.borrow_mut() = Some(f.await);
+            });
+        }
+
+        Self::executor_loop(|| result.borrow().is_some());
+
+        result.take().unwrap()
+    }
+
+    /// Cancel all tasks on this thread and tear down the reactor.
+    ///
+    /// Every task that has been spawned on this thread and has not yet
+    /// finished is dropped without being polled again. Dropping a task drops
+    /// any in-flight I/O that it owns, which is cancelled in the kernel. Once
+    /// all tasks have been dropped, any I/O that is still outstanding is
+    /// cancelled and its completion reaped, such that when this function
+    /// returns no operations are running on this thread's ring.
+    ///
+    /// *Note* A [TaskJoiner] for a task that has been cancelled will never
+    /// complete; calling [TaskJoiner::join] on it will panic.
+    pub fn shutdown() {
+        loop {
+            // Take the tasks out of the executor before dropping them, since
+            // dropping a task may cause other tasks to be woken.
+            let (waiting, run_q) = EXEC.with(|exec| {
+                let mut exec = exec.borrow_mut();
+                (
+                    std::mem::take(&mut exec.waiting),
+                    std::mem::replace(&mut exec.run_q, RunQueue::new()),
+                )
+            });
+
+            if waiting.is_empty() && run_q.is_empty() {
+                break;
+            }
+        }
+
+        let listener = EXEC.with(|exec| exec.borrow_mut().remote_listener.take());
+        drop(listener);
+
+        Reactor::shutdown();
+    }
+
     /// Run the executor for this thread.
     ///
     /// This function will schedule and run all tasks that have been previously
@@ -259,37 +719,462 @@ impl Executor {
     ///
     /// Blocks until all tasks have finished executing.
     pub fn run() {
-        Self::executor_loop()
+        Self::executor_loop(|| false)
+    }
+
+    /// Returns `true` if the kernel supports the io_uring operation `opcode`.
+    ///
+    /// Supported operations are probed when this thread's reactor is
+    /// created. Where trale relies on an operation that is missing it falls
+    /// back to an alternative, e.g. an eventfd instead of a futex; otherwise
+    /// the operation fails with [std::io::ErrorKind::Unsupported].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use io_uring::opcode;
+    /// use trale::task::Executor;
+    /// assert!(Executor::is_supported(opcode::Read::CODE));
+    /// ```
+    pub fn is_supported(opcode: u8) -> bool {
+        Reactor::is_supported(opcode)
+    }
+
+    /// Obtain a snapshot of this thread's executor metrics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trale::task::Executor;
+    /// Executor::spawn(async {});
+    /// Executor::run();
+    /// let metrics = Executor::metrics();
+    /// assert_eq!(metrics.tasks_spawned, metrics.tasks_completed);
+    /// ```
+    pub fn metrics() -> Metrics {
+        let reactor = Reactor::metrics();
+        let mut metrics = EXEC.with(|exec| exec.borrow().metrics);
+
+        metrics.sqes_submitted = reactor.sqes_submitted;
+        metrics.reacts = reactor.reacts;
+        metrics.cqes_reaped = reactor.cqes_reaped;
+        metrics.max_cqes_per_react = reactor.max_cqes_per_react;
+        metrics.inflight_oneshot = reactor.inflight_oneshot;
+        metrics.inflight_multishot = reactor.inflight_multishot;
+
+        metrics
     }
 
-    fn executor_loop() {
+    /// Run the executor for this thread without blocking.
+    ///
+    /// This function reaps any I/O completions that are available without
+    /// waiting, polls each task that is ready to run once and then submits any
+    /// I/O that those tasks have queued to the kernel. It is intended for
+    /// embedding trale within another event loop which must retain ownership
+    /// of the thread, see [Executor::completion_fd].
+    ///
+    /// Returns `true` if tasks are still ready to run, in which case the caller
+    /// should call this function again before sleeping.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trale::task::Executor;
+    /// let task = Executor::spawn(async { 2 + 8 });
+    /// while Executor::tick() {}
+    /// assert_eq!(task.join(), 10);
+    /// ```
+    pub fn tick() -> bool {
+        let start = Instant::now();
+        Reactor::poll();
+        EXEC.with(|exec| exec.borrow_mut().metrics.react_time += start.elapsed());
+
+        let ready = EXEC.with(|exec| exec.borrow().run_q.len());
+
+        for _ in 0..ready {
+            Self::poll_one();
+        }
+
+        Self::arm_remote_wakes();
+        Reactor::submit();
+
+        EXEC.with(|exec| !exec.borrow().run_q.is_empty())
+    }
+
+    /// Obtain a file descriptor which becomes readable whenever an I/O
+    /// completion is posted to this thread's reactor.
+    ///
+    /// The returned descriptor is an `eventfd` that is registered with the
+    /// underlying `io_uring`. It can be added to an external `epoll` set or
+    /// event loop, which should call [Executor::tick] whenever it becomes
+    /// readable. The descriptor is owned by the reactor and remains valid for
+    /// the lifetime of the thread; it is reset by [Executor::tick].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trale::futures::timer::Timer;
+    /// use trale::task::Executor;
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    /// use std::time::Duration;
+    /// let fd = Executor::completion_fd().unwrap();
+    /// let done = Rc::new(Cell::new(false));
+    /// let done2 = done.clone();
+    /// Executor::spawn(async move {
+    ///     Timer::sleep(Duration::from_millis(10)).unwrap().await;
+    ///     done2.set(true);
+    /// });
+    ///
+    /// loop {
+    ///     let busy = Executor::tick();
+    ///
+    ///     if done.get() {
+    ///         break;
+    ///     }
+    ///
+    ///     if !busy {
+    ///         let mut pfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    ///         unsafe { libc::poll(&mut pfd, 1, -1) };
+    ///     }
+    /// }
+    /// ```
+    pub fn completion_fd() -> std::io::Result<RawFd> {
+        Reactor::completion_fd()
+    }
+
+    fn executor_loop(done: impl Fn() -> bool) {
         EXEC.with(|exec| loop {
+            if done() || (exec.borrow().run_q.is_empty() && exec.borrow().waiting.is_empty()) {
+                return;
+            }
+
             if exec.borrow().run_q.is_empty() {
+                Self::arm_remote_wakes();
+            }
+
+            // Arming may have taken a pending remote wakeup and queued its
+            // tasks, in which case they must run before we sleep.
+            if exec.borrow().run_q.is_empty() {
+                let start = Instant::now();
                 Reactor::react();
+                exec.borrow_mut().metrics.react_time += start.elapsed();
             }
 
-            let mut task = exec.borrow_mut().run_q.pop().unwrap();
+            Self::poll_one();
+        });
+    }
+
+    /// Pop the next task from the run queue, if any, and poll it.
+    fn poll_one() {
+        EXEC.with(|exec| {
+            let Some(mut task) = exec.borrow_mut().run_q.pop() else {
+                return;
+            };
 
             let waker = Waker::from(task.id.clone());
 
             let mut cx = Context::from_waker(&waker);
 
-            match task.future.as_mut().poll(&mut cx) {
-                Poll::Ready(()) => {}
+            task.id.notified.store(false, Ordering::Relaxed);
+
+            #[cfg(feature = "tracing")]
+            let _enter = task.span.clone().entered();
+
+            let start = Instant::now();
+            let result = task.future.as_mut().poll(&mut cx);
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                ready = result.is_ready(),
+                elapsed_us = start.elapsed().as_micros() as u64,
+                "polled"
+            );
+
+            {
+                let metrics = &mut exec.borrow_mut().metrics;
+                metrics.polls += 1;
+                metrics.poll_time += start.elapsed();
+            }
+
+            match result {
+                Poll::Ready(()) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("completed");
+
+                    exec.borrow_mut().metrics.tasks_completed += 1;
+                }
+                Poll::Pending if task.id.notified.swap(false, Ordering::Relaxed) => {
+                    exec.borrow_mut().push(task);
+                }
                 Poll::Pending => {
                     let waiting = &mut exec.borrow_mut().waiting;
 
                     let slot = waiting.vacant_entry();
 
-                    task.id.0.store(slot.key(), Ordering::Relaxed);
+                    task.id.slot.store(slot.key(), Ordering::Relaxed);
 
                     slot.insert(task);
                 }
             }
+        });
+    }
+}
 
-            if exec.borrow().run_q.is_empty() && exec.borrow().waiting.is_empty() {
-                return;
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        future::{poll_fn, Future},
+        net::Ipv4Addr,
+        pin::Pin,
+        rc::Rc,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc,
+        },
+        task::{Context, Poll, Waker},
+        thread,
+        time::Duration,
+    };
+
+    use super::{Builder, Executor, Priority};
+    use crate::futures::{timer::Timer, udp::UdpSocket};
+
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if self.0 {
+                return Poll::Ready(());
             }
+
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn priority_order() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            let order = order.clone();
+            Builder::new()
+                .priority(priority)
+                .spawn(async move { order.borrow_mut().push(priority) });
+        }
+
+        Executor::run();
+
+        assert_eq!(
+            *order.borrow(),
+            [Priority::High, Priority::Normal, Priority::Low]
+        );
+    }
+
+    #[test]
+    fn metrics() {
+        Executor::block_on(async {
+            Executor::spawn(async {
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+            })
+            .await;
+        });
+
+        let metrics = Executor::metrics();
+
+        assert_eq!(metrics.tasks_spawned, 2);
+        assert_eq!(metrics.tasks_completed, 2);
+        assert!(metrics.polls >= 4);
+        assert!(metrics.wakeups >= 2);
+        assert!(metrics.sqes_submitted >= 2);
+        assert_eq!(metrics.max_run_queue_depth, 1);
+        assert_eq!(metrics.inflight_multishot, 0);
+    }
+
+    #[test]
+    fn run_until_leaves_tasks() {
+        let ticks = Rc::new(RefCell::new(0));
+
+        {
+            let ticks = ticks.clone();
+            Executor::spawn(async move {
+                loop {
+                    Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                    *ticks.borrow_mut() += 1;
+                }
+            });
+        }
+
+        let x = Executor::run_until(async {
+            Timer::sleep(Duration::from_millis(55)).unwrap().await;
+            10
         });
+
+        assert_eq!(x, 10);
+        assert!(*ticks.borrow() >= 4);
+
+        Executor::run_until(Timer::sleep(Duration::from_millis(55)).unwrap());
+
+        assert!(*ticks.borrow() >= 8);
+
+        Executor::shutdown();
+    }
+
+    #[test]
+    fn shutdown_cancels_tasks() {
+        struct DropFlag(Rc<RefCell<bool>>);
+
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                *self.0.borrow_mut() = true;
+            }
+        }
+
+        let dropped = Rc::new(RefCell::new(false));
+
+        {
+            let flag = DropFlag(dropped.clone());
+            Executor::spawn(async move {
+                let _flag = flag;
+                let mut sock = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+                let mut buf = [0; 4];
+                sock.recv_from(&mut buf).await.unwrap();
+            });
+        }
+
+        Executor::run_until(Timer::sleep(Duration::from_millis(10)).unwrap());
+
+        assert!(!*dropped.borrow());
+        // The UDP receive and the executor's listener for remote wakeups.
+        assert_eq!(Executor::metrics().inflight_oneshot, 2);
+
+        Executor::shutdown();
+
+        assert!(*dropped.borrow());
+        assert_eq!(Executor::metrics().inflight_oneshot, 0);
+    }
+
+    #[test]
+    fn tick_with_completion_fd() {
+        let fd = Executor::completion_fd().unwrap();
+        let done = Rc::new(RefCell::new(false));
+
+        {
+            let done = done.clone();
+            Executor::spawn(async move {
+                Timer::sleep(Duration::from_millis(10)).unwrap().await;
+                *done.borrow_mut() = true;
+            });
+        }
+
+        let mut sleeps = 0;
+
+        loop {
+            let busy = Executor::tick();
+
+            if *done.borrow() {
+                break;
+            }
+
+            if !busy {
+                let mut pfd = libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+
+                assert_eq!(unsafe { libc::poll(&mut pfd, 1, 1000) }, 1);
+                sleeps += 1;
+            }
+        }
+
+        assert!(sleeps >= 1);
+        assert_eq!(Executor::completion_fd().unwrap(), fd);
+    }
+
+    #[test]
+    fn remote_wake() {
+        let (tx, rx) = mpsc::channel::<Waker>();
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let t1 = {
+            let flag = flag.clone();
+            thread::spawn(move || {
+                let waker = rx.recv().unwrap();
+                thread::sleep(Duration::from_millis(50));
+                flag.store(true, Ordering::Relaxed);
+                waker.wake();
+            })
+        };
+
+        Executor::block_on(poll_fn(move |cx| {
+            if flag.load(Ordering::Relaxed) {
+                return Poll::Ready(());
+            }
+
+            let _ = tx.send(cx.waker().clone());
+            Poll::Pending
+        }));
+
+        t1.join().unwrap();
+    }
+
+    #[test]
+    fn remote_wake_during_poll() {
+        let (tx, rx) = mpsc::channel::<Waker>();
+        let (done_tx, done_rx) = mpsc::channel();
+
+        let t1 = thread::spawn(move || {
+            rx.recv().unwrap().wake();
+            done_tx.send(()).unwrap();
+        });
+
+        // The wakeup is already pending when the executor next checks for
+        // remote wakes, so it must poll the task rather than sleep.
+        let mut polled = false;
+        Executor::block_on(poll_fn(move |cx| {
+            if std::mem::replace(&mut polled, true) {
+                return Poll::Ready(());
+            }
+
+            tx.send(cx.waker().clone()).unwrap();
+            done_rx.recv().unwrap();
+            Poll::Pending
+        }));
+
+        t1.join().unwrap();
+    }
+
+    #[test]
+    fn low_priority_not_starved() {
+        let high_iters = Rc::new(RefCell::new(0));
+        let low_ran_at = Rc::new(RefCell::new(None));
+
+        {
+            let high_iters = high_iters.clone();
+            Builder::new().priority(Priority::High).spawn(async move {
+                for _ in 0..1000 {
+                    *high_iters.borrow_mut() += 1;
+                    YieldNow(false).await;
+                }
+            });
+        }
+
+        {
+            let high_iters = high_iters.clone();
+            let low_ran_at = low_ran_at.clone();
+            Builder::new().priority(Priority::Low).spawn(async move {
+                *low_ran_at.borrow_mut() = Some(*high_iters.borrow());
+            });
+        }
+
+        Executor::run();
+
+        assert!(low_ran_at.borrow().unwrap() < 1000);
     }
 }